@@ -1,16 +1,27 @@
 use thiserror::Error;
 
-use oasis_core_runtime::consensus::{state::StateError, verifier};
+use oasis_core_runtime::{
+    common::{crypto::signature::PublicKey, namespace::Namespace},
+    consensus::{state::StateError, verifier},
+};
 
 /// Key manager error.
 #[derive(Error, Debug)]
 pub enum KeyManagerError {
     #[error("client session is not authenticated")]
     NotAuthenticated,
+    #[error("client session has expired")]
+    SessionExpired,
     #[error("client is not authorized")]
     NotAuthorized,
+    #[error("key manager unreachable: {0}")]
+    Unreachable(String),
+    #[error("deadline exceeded")]
+    DeadlineExceeded,
     #[error("invalid epoch: expected {0}, got {1}")]
     InvalidEpoch(u64, u64),
+    #[error("secret not yet accessible: available at epoch {available_at_epoch}")]
+    SecretNotYetAccessible { available_at_epoch: u64 },
     #[error("invalid generation: expected {0}, got {1}")]
     InvalidGeneration(u64, u64),
     #[error("generation is in the future: expected max {0}, got {1}")]
@@ -23,6 +34,12 @@ pub enum KeyManagerError {
     StateCorrupted,
     #[error("key manager storage corrupted")]
     StorageCorrupted,
+    #[error("key manager state diverged at generation {generation}")]
+    DivergentState { generation: u64 },
+    #[error("key manager sealing error: {0}")]
+    SealingError(String),
+    #[error("insufficient entropy for secret generation")]
+    InsufficientEntropy,
     #[error("policy required")]
     PolicyRequired,
     #[error("policy rollback")]
@@ -31,10 +48,16 @@ pub enum KeyManagerError {
     PolicyChanged,
     #[error("policy has invalid runtime")]
     PolicyInvalidRuntime,
+    #[error("replication of generation {generation} disallowed by policy")]
+    ReplicationDisallowedByPolicy { generation: u64 },
     #[error("insufficient key shares")]
     InsufficientKeyShares,
+    #[error("committee quorum unavailable: {online} online, {required} required")]
+    CommitteeQuorumUnavailable { online: usize, required: usize },
     #[error("insufficient signatures")]
     InsufficientSignatures,
+    #[error("policy signer {signer} has been revoked")]
+    PolicySignerRevoked { signer: PublicKey },
     #[error("runtime signing key missing")]
     RSKMissing,
     #[error("runtime encryption key not published")]
@@ -59,12 +82,18 @@ pub enum KeyManagerError {
     EphemeralSecretChecksumMismatch,
     #[error("invalid ciphertext")]
     InvalidCiphertext,
+    #[error("invalid key material: expected {expected_len} bytes, got {got_len}")]
+    InvalidKeyMaterial { expected_len: usize, got_len: usize },
     #[error("status not found")]
     StatusNotFound,
-    #[error("runtime mismatch")]
-    RuntimeMismatch,
+    #[error("runtime mismatch: expected {expected}, got {got}")]
+    RuntimeMismatch { expected: Namespace, got: Namespace },
     #[error("active deployment not found")]
     ActiveDeploymentNotFound,
+    #[error("feature disabled: {feature}")]
+    FeatureDisabled { feature: String },
+    #[error("node attestation is stale or revoked: {0}")]
+    NodeAttestationStale(PublicKey),
     #[error("state error: {0}")]
     StateError(#[from] StateError),
     #[error("verification error: {0}")]
@@ -72,3 +101,40 @@ pub enum KeyManagerError {
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
+
+impl KeyManagerError {
+    /// Whether the error is transient, i.e. the request may succeed if retried
+    /// after the cause has been resolved (e.g. by re-establishing the session).
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            KeyManagerError::SessionExpired
+                | KeyManagerError::Unreachable(_)
+                | KeyManagerError::InsufficientEntropy
+                | KeyManagerError::CommitteeQuorumUnavailable { .. }
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use oasis_core_runtime::common::namespace::Namespace;
+
+    use super::KeyManagerError;
+
+    #[test]
+    fn test_runtime_mismatch_display() {
+        let expected = Namespace::from(vec![1; 32]);
+        let got = Namespace::from(vec![2; 32]);
+        let msg = KeyManagerError::RuntimeMismatch { expected, got }.to_string();
+
+        assert!(msg.contains(&expected.to_string()));
+        assert!(msg.contains(&got.to_string()));
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(KeyManagerError::SessionExpired.is_transient());
+        assert!(!KeyManagerError::NotAuthenticated.is_transient());
+    }
+}
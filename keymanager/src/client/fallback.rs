@@ -0,0 +1,372 @@
+//! Key manager client which fails over between multiple key managers.
+use std::{future::Future, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+
+use oasis_core_runtime::{
+    common::{crypto::signature::PublicKey, namespace::Namespace},
+    consensus::beacon::EpochTime,
+};
+
+use crate::{
+    api::KeyManagerError,
+    churp::EncodedVerifiableSecretShare,
+    crypto::{KeyPair, KeyPairId, Secret, SignedPublicKey, StateKey, VerifiableSecret},
+};
+
+use super::KeyManagerClient;
+
+/// A key manager client which forwards requests to an ordered list of key manager clients.
+///
+/// Requests are sent to the first (primary) client. If it fails with a transient error,
+/// the request is retried once with each of the following clients, in order. Non-transient
+/// errors are returned immediately, even if an earlier client failed transiently, so that
+/// permanent failures are not retried. If all clients fail with transient errors, the
+/// primary's error is returned.
+///
+/// If a deadline is configured, it bounds the time spent on a request across all clients.
+pub struct FallbackClient {
+    clients: Vec<Arc<dyn KeyManagerClient>>,
+    deadline: Option<Duration>,
+}
+
+impl FallbackClient {
+    /// Create a new fallback client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no clients are given.
+    pub fn new(clients: Vec<Arc<dyn KeyManagerClient>>) -> Self {
+        assert!(!clients.is_empty(), "at least one client is required");
+        Self {
+            clients,
+            deadline: None,
+        }
+    }
+
+    /// Fail requests with `DeadlineExceeded` if no client responds in the given time.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    async fn call<'a, T, F, Fut>(&'a self, f: F) -> Result<T, KeyManagerError>
+    where
+        F: Fn(&'a Arc<dyn KeyManagerClient>) -> Fut,
+        Fut: Future<Output = Result<T, KeyManagerError>>,
+    {
+        with_deadline(self.deadline, with_fallback(&self.clients, f)).await
+    }
+}
+
+/// Wait for the given future to complete, failing if the deadline (if any) passes first.
+async fn with_deadline<T, Fut>(
+    deadline: Option<Duration>,
+    future: Fut,
+) -> Result<T, KeyManagerError>
+where
+    Fut: Future<Output = Result<T, KeyManagerError>>,
+{
+    match deadline {
+        Some(deadline) => tokio::time::timeout(deadline, future)
+            .await
+            .map_err(|_| KeyManagerError::DeadlineExceeded)?,
+        None => future.await,
+    }
+}
+
+/// Call the given function with each client in turn until one succeeds or fails
+/// with a non-transient error, which is returned as is.
+async fn with_fallback<'a, C, T, F, Fut>(clients: &'a [C], f: F) -> Result<T, KeyManagerError>
+where
+    F: Fn(&'a C) -> Fut,
+    Fut: Future<Output = Result<T, KeyManagerError>>,
+{
+    let mut primary_err = None;
+
+    for client in clients {
+        let err = match f(client).await {
+            Ok(result) => return Ok(result),
+            Err(err) => err,
+        };
+
+        if !err.is_transient() {
+            return Err(err);
+        }
+
+        primary_err.get_or_insert(err);
+    }
+
+    Err(primary_err.unwrap_or(KeyManagerError::NotInitialized))
+}
+
+#[async_trait]
+impl KeyManagerClient for FallbackClient {
+    fn runtime_id(&self) -> Option<Namespace> {
+        self.clients.iter().find_map(|client| client.runtime_id())
+    }
+
+    fn runtime_signing_key(&self) -> Option<PublicKey> {
+        self.clients
+            .iter()
+            .find_map(|client| client.runtime_signing_key())
+    }
+
+    fn clear_cache(&self) {
+        for client in &self.clients {
+            client.clear_cache();
+        }
+    }
+
+    async fn get_or_create_keys(
+        &self,
+        key_pair_id: KeyPairId,
+        generation: u64,
+    ) -> Result<KeyPair, KeyManagerError> {
+        self.call(|client| client.get_or_create_keys(key_pair_id, generation))
+            .await
+    }
+
+    async fn get_public_key(
+        &self,
+        key_pair_id: KeyPairId,
+        generation: u64,
+    ) -> Result<SignedPublicKey, KeyManagerError> {
+        self.call(|client| client.get_public_key(key_pair_id, generation))
+            .await
+    }
+
+    async fn get_or_create_ephemeral_keys(
+        &self,
+        key_pair_id: KeyPairId,
+        epoch: EpochTime,
+    ) -> Result<KeyPair, KeyManagerError> {
+        self.call(|client| client.get_or_create_ephemeral_keys(key_pair_id, epoch))
+            .await
+    }
+
+    async fn get_public_ephemeral_key(
+        &self,
+        key_pair_id: KeyPairId,
+        epoch: EpochTime,
+    ) -> Result<SignedPublicKey, KeyManagerError> {
+        self.call(|client| client.get_public_ephemeral_key(key_pair_id, epoch))
+            .await
+    }
+
+    async fn replicate_master_secret(
+        &self,
+        generation: u64,
+        nodes: Vec<PublicKey>,
+    ) -> Result<VerifiableSecret, KeyManagerError> {
+        self.call(|client| client.replicate_master_secret(generation, nodes.clone()))
+            .await
+    }
+
+    async fn replicate_ephemeral_secret(
+        &self,
+        epoch: EpochTime,
+        nodes: Vec<PublicKey>,
+    ) -> Result<Secret, KeyManagerError> {
+        self.call(|client| client.replicate_ephemeral_secret(epoch, nodes.clone()))
+            .await
+    }
+
+    async fn churp_verification_matrix(
+        &self,
+        churp_id: u8,
+        epoch: EpochTime,
+        nodes: Vec<PublicKey>,
+    ) -> Result<Vec<u8>, KeyManagerError> {
+        self.call(|client| client.churp_verification_matrix(churp_id, epoch, nodes.clone()))
+            .await
+    }
+
+    async fn churp_share_reduction_point(
+        &self,
+        churp_id: u8,
+        epoch: EpochTime,
+        node_id: PublicKey,
+        nodes: Vec<PublicKey>,
+    ) -> Result<Vec<u8>, KeyManagerError> {
+        self.call(|client| {
+            client.churp_share_reduction_point(churp_id, epoch, node_id, nodes.clone())
+        })
+        .await
+    }
+
+    async fn churp_share_distribution_point(
+        &self,
+        churp_id: u8,
+        epoch: EpochTime,
+        node_id: PublicKey,
+        nodes: Vec<PublicKey>,
+    ) -> Result<Vec<u8>, KeyManagerError> {
+        self.call(|client| {
+            client.churp_share_distribution_point(churp_id, epoch, node_id, nodes.clone())
+        })
+        .await
+    }
+
+    async fn churp_bivariate_share(
+        &self,
+        churp_id: u8,
+        epoch: EpochTime,
+        node_id: PublicKey,
+        nodes: Vec<PublicKey>,
+    ) -> Result<EncodedVerifiableSecretShare, KeyManagerError> {
+        self.call(|client| client.churp_bivariate_share(churp_id, epoch, node_id, nodes.clone()))
+            .await
+    }
+
+    async fn churp_state_key(
+        &self,
+        churp_id: u8,
+        key_id: KeyPairId,
+    ) -> Result<StateKey, KeyManagerError> {
+        self.call(|client| client.churp_state_key(churp_id, key_id))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, future, time::Duration};
+
+    use futures::executor::block_on;
+
+    use crate::api::KeyManagerError;
+
+    use super::{with_deadline, with_fallback};
+
+    /// Test client which returns a fixed result and records each call.
+    struct TestClient {
+        name: &'static str,
+        result: fn() -> Result<u64, KeyManagerError>,
+    }
+
+    fn call(clients: &[TestClient]) -> (Result<u64, KeyManagerError>, Vec<&'static str>) {
+        let calls = RefCell::new(Vec::new());
+        let result = block_on(with_fallback(clients, |client: &TestClient| {
+            calls.borrow_mut().push(client.name);
+            let result = (client.result)();
+            async move { result }
+        }));
+        (result, calls.into_inner())
+    }
+
+    #[test]
+    fn test_fallback_on_transient_error() {
+        let clients = [
+            TestClient {
+                name: "primary",
+                result: || Err(KeyManagerError::Unreachable("transport error".into())),
+            },
+            TestClient {
+                name: "secondary",
+                result: || Ok(2),
+            },
+        ];
+
+        let (result, calls) = call(&clients);
+        assert_eq!(result.expect("secondary should succeed"), 2);
+        assert_eq!(calls, vec!["primary", "secondary"]);
+    }
+
+    #[test]
+    fn test_no_fallback_on_permanent_error() {
+        let clients = [
+            TestClient {
+                name: "primary",
+                result: || Err(KeyManagerError::NotAuthorized),
+            },
+            TestClient {
+                name: "secondary",
+                result: || Ok(2),
+            },
+        ];
+
+        let (result, calls) = call(&clients);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::NotAuthorized.to_string()
+        );
+        assert_eq!(calls, vec!["primary"]);
+    }
+
+    #[test]
+    fn test_failover_stops_on_permanent_error() {
+        let clients = [
+            TestClient {
+                name: "primary",
+                result: || Err(KeyManagerError::SessionExpired),
+            },
+            TestClient {
+                name: "secondary",
+                result: || Err(KeyManagerError::NotAuthorized),
+            },
+            TestClient {
+                name: "tertiary",
+                result: || Ok(3),
+            },
+        ];
+
+        // Failover stops at the first non-transient error, which is returned.
+        let (result, calls) = call(&clients);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::NotAuthorized.to_string()
+        );
+        assert_eq!(calls, vec!["primary", "secondary"]);
+
+        // If all clients fail with transient errors, the primary's error is returned.
+        let (result, calls) = call(&clients[..1]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::SessionExpired.to_string()
+        );
+        assert_eq!(calls, vec!["primary"]);
+
+        let clients = [
+            TestClient {
+                name: "primary",
+                result: || Err(KeyManagerError::SessionExpired),
+            },
+            TestClient {
+                name: "secondary",
+                result: || Err(KeyManagerError::Unreachable("transport error".into())),
+            },
+        ];
+        let (result, calls) = call(&clients);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::SessionExpired.to_string()
+        );
+        assert_eq!(calls, vec!["primary", "secondary"]);
+    }
+
+    #[test]
+    fn test_deadline() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        let result = rt.block_on(with_deadline(
+            Some(Duration::from_millis(10)),
+            future::pending::<Result<u64, KeyManagerError>>(),
+        ));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::DeadlineExceeded.to_string()
+        );
+
+        let result = rt.block_on(with_deadline(Some(Duration::from_secs(60)), async {
+            Ok(1)
+        }));
+        assert_eq!(result.expect("call should complete in time"), 1);
+
+        let result = rt.block_on(with_deadline(None, async { Ok(1) }));
+        assert_eq!(result.expect("call should complete"), 1);
+    }
+}
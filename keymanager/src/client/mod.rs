@@ -1,7 +1,16 @@
 //! Key manager client.
+mod fallback;
 mod interface;
 mod mock;
 mod remote;
+mod replication;
+mod status;
 
 // Re-exports.
-pub use self::{interface::KeyManagerClient, mock::MockClient, remote::RemoteClient};
+pub use self::{
+    fallback::FallbackClient,
+    interface::KeyManagerClient,
+    mock::MockClient,
+    remote::{CacheKind, RemoteClient},
+    replication::{replicate_generations_stream, GenerationReplicationResult},
+};
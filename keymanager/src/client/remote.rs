@@ -2,14 +2,16 @@
 use std::{
     collections::HashSet,
     convert::TryInto,
+    future::Future,
     iter::FromIterator,
     num::NonZeroUsize,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 
 use anyhow::anyhow;
 use async_trait::async_trait;
-use futures::stream::{FuturesUnordered, StreamExt};
+use futures::stream::{BoxStream, FuturesUnordered, StreamExt};
 use group::GroupEncoding;
 use lru::LruCache;
 use rand::{prelude::SliceRandom, rngs::OsRng};
@@ -23,14 +25,21 @@ use oasis_core_runtime::{
     consensus::{
         beacon::EpochTime,
         keymanager::churp::{self, Status as ChurpStatus, SuiteId},
+        registry::{CapabilityTEE, Node},
         state::{
             beacon::ImmutableState as BeaconState,
-            keymanager::{churp::ImmutableState as ChurpState, Status as KeyManagerStatus},
+            keymanager::{
+                churp::ImmutableState as ChurpState, ImmutableState as KeyManagerState,
+                Status as KeyManagerStatus,
+            },
             registry::ImmutableState as RegistryState,
         },
         verifier::Verifier,
     },
-    enclave_rpc::{client::RpcClient, session},
+    enclave_rpc::{
+        client::{Response, RpcClient, RpcClientError},
+        session,
+    },
     identity::Identity,
     protocol::Protocol,
 };
@@ -60,7 +69,10 @@ use crate::{
     policy::{set_trusted_signers, verify_data_and_trusted_signers, Policy, TrustedSigners},
 };
 
-use super::KeyManagerClient;
+use super::{
+    status::{StatusCache, StatusWatchers},
+    KeyManagerClient,
+};
 
 /// Key manager RPC endpoint.
 const KEY_MANAGER_ENDPOINT: &str = "key-manager";
@@ -72,15 +84,37 @@ const RPC_MAX_SESSIONS_PER_PEER: usize = 2;
 /// EnclaveRPC sessions without any processed frame for more than RPC_STALE_SESSION_TIMEOUT_SECS
 /// seconds can be closed to make room for new sessions.
 const RPC_STALE_SESSION_TIMEOUT_SECS: i64 = 10;
+/// Time window in which requests for ephemeral keys of the current or the next epoch are
+/// retried while the ephemeral secret for that epoch is not yet available.
+const EPHEMERAL_SECRET_POLL_WINDOW: Duration = Duration::from_secs(2);
+/// Initial delay between retries of requests for ephemeral keys, doubled after each retry.
+const EPHEMERAL_SECRET_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Kind of a local cache maintained by the remote client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheKind {
+    /// Long-term private and public keys.
+    LongTermKeys,
+    /// Ephemeral private and public keys.
+    EphemeralKeys,
+    /// CHURP state keys.
+    StateKeys,
+    /// Key manager statuses.
+    Statuses,
+}
 
-/// A key manager client which talks to a remote key manager enclave.
-pub struct RemoteClient {
-    /// Runtime identifier for which we are going to request keys.
-    runtime_id: Namespace,
-    /// RPC client.
-    rpc_client: RpcClient,
-    /// Consensus verifier.
-    consensus_verifier: Arc<dyn Verifier>,
+impl CacheKind {
+    /// All cache kinds.
+    pub const ALL: [CacheKind; 4] = [
+        CacheKind::LongTermKeys,
+        CacheKind::EphemeralKeys,
+        CacheKind::StateKeys,
+        CacheKind::Statuses,
+    ];
+}
+
+/// Local caches maintained by the remote client.
+struct Caches {
     /// Local cache for the long-term private keys.
     longterm_private_keys: RwLock<LruCache<(KeyPairId, u64), KeyPair>>,
     /// Local cache for the long-term public keys.
@@ -91,6 +125,73 @@ pub struct RemoteClient {
     ephemeral_public_keys: RwLock<LruCache<(KeyPairId, EpochTime), SignedPublicKey>>,
     /// Local cache for the state keys.
     state_keys: RwLock<LruCache<(KeyPairId, u8), StateKey>>,
+    /// Local cache for the key manager statuses.
+    statuses: StatusCache,
+}
+
+impl Caches {
+    /// Create new empty caches, each holding at most the given number of entries.
+    fn new(cap: NonZeroUsize) -> Self {
+        Self {
+            longterm_private_keys: RwLock::new(LruCache::new(cap)),
+            longterm_public_keys: RwLock::new(LruCache::new(cap)),
+            ephemeral_private_keys: RwLock::new(LruCache::new(cap)),
+            ephemeral_public_keys: RwLock::new(LruCache::new(cap)),
+            state_keys: RwLock::new(LruCache::new(cap)),
+            statuses: StatusCache::new(),
+        }
+    }
+
+    /// Clear the given cache.
+    fn invalidate(&self, kind: CacheKind) {
+        // We explicitly only take one lock at a time.
+        match kind {
+            CacheKind::LongTermKeys => {
+                let mut cache = self.longterm_private_keys.write().unwrap();
+                cache.clear();
+                drop(cache);
+
+                let mut cache = self.longterm_public_keys.write().unwrap();
+                cache.clear();
+            }
+            CacheKind::EphemeralKeys => {
+                let mut cache = self.ephemeral_private_keys.write().unwrap();
+                cache.clear();
+                drop(cache);
+
+                let mut cache = self.ephemeral_public_keys.write().unwrap();
+                cache.clear();
+            }
+            CacheKind::StateKeys => {
+                let mut cache = self.state_keys.write().unwrap();
+                cache.clear();
+            }
+            CacheKind::Statuses => self.statuses.clear(),
+        }
+    }
+
+    /// Clear all caches.
+    fn invalidate_all(&self) {
+        for kind in CacheKind::ALL {
+            self.invalidate(kind);
+        }
+    }
+}
+
+/// A key manager client which talks to a remote key manager enclave.
+pub struct RemoteClient {
+    /// Runtime identifier for which we are going to request keys.
+    runtime_id: Namespace,
+    /// RPC client.
+    rpc_client: RpcClient,
+    /// Consensus verifier.
+    consensus_verifier: Arc<dyn Verifier>,
+    /// Key manager's quote policy, used to verify node attestations.
+    quote_policy: RwLock<Option<Arc<QuotePolicy>>>,
+    /// Local caches.
+    caches: Caches,
+    /// Subscribers to key manager status changes.
+    status_watchers: StatusWatchers,
     /// Key manager runtime ID.
     key_manager_id: RwLock<Option<Namespace>>,
     /// Key manager's runtime signing key.
@@ -102,6 +203,7 @@ impl RemoteClient {
         runtime_id: Namespace,
         rpc_client: RpcClient,
         consensus_verifier: Arc<dyn Verifier>,
+        quote_policy: Option<Arc<QuotePolicy>>,
         keys_cache_sizes: usize,
     ) -> Self {
         let cap = NonZeroUsize::new(keys_cache_sizes).unwrap();
@@ -110,11 +212,9 @@ impl RemoteClient {
             runtime_id,
             rpc_client,
             consensus_verifier,
-            longterm_private_keys: RwLock::new(LruCache::new(cap)),
-            longterm_public_keys: RwLock::new(LruCache::new(cap)),
-            ephemeral_private_keys: RwLock::new(LruCache::new(cap)),
-            ephemeral_public_keys: RwLock::new(LruCache::new(cap)),
-            state_keys: RwLock::new(LruCache::new(cap)),
+            quote_policy: RwLock::new(quote_policy),
+            caches: Caches::new(cap),
+            status_watchers: StatusWatchers::new(),
             key_manager_id: RwLock::new(None),
             rsk: RwLock::new(None),
         }
@@ -135,7 +235,7 @@ impl RemoteClient {
     ) -> Self {
         let builder = session::Builder::default()
             .remote_enclaves(enclaves)
-            .quote_policy(policy)
+            .quote_policy(policy.clone())
             .local_identity(identity)
             .consensus_verifier(Some(consensus_verifier.clone()))
             .remote_runtime_id(km_runtime_id);
@@ -151,6 +251,7 @@ impl RemoteClient {
                 RPC_STALE_SESSION_TIMEOUT_SECS,
             ),
             consensus_verifier,
+            policy,
             keys_cache_sizes,
         )
     }
@@ -213,28 +314,134 @@ impl RemoteClient {
         self.rpc_client.update_runtime_id(Some(status.id)).await;
 
         // Verify and apply the policy, if set.
-        let untrusted_policy = match status.policy {
-            Some(policy) => policy,
-            None => return Ok(()),
-        };
-
-        let policy = verify_data_and_trusted_signers(&untrusted_policy)?;
+        if let Some(untrusted_policy) = &status.policy {
+            let policy = verify_data_and_trusted_signers(untrusted_policy)?;
+
+            // Set client allowed enclaves from key manager policy.
+            if !Policy::unsafe_skip() {
+                let enclaves: HashSet<EnclaveIdentity> =
+                    HashSet::from_iter(policy.enclaves.keys().cloned());
+                self.rpc_client.update_enclaves(Some(enclaves)).await;
+            }
+        }
 
-        // Set client allowed enclaves from key manager policy.
-        if !Policy::unsafe_skip() {
-            let enclaves: HashSet<EnclaveIdentity> =
-                HashSet::from_iter(policy.enclaves.keys().cloned());
-            self.rpc_client.update_enclaves(Some(enclaves)).await;
+        // Notify status watchers and drop the stale cached status, now that the policy
+        // has been verified.
+        if self.status_watchers.notify(&status) {
+            self.caches.statuses.invalidate(&status.id);
         }
 
         Ok(())
     }
 
+    /// Key manager status for the given key manager runtime.
+    ///
+    /// Statuses are cached until the next epoch transition.
+    pub async fn status(&self, runtime_id: Namespace) -> Result<KeyManagerStatus, KeyManagerError> {
+        let key_manager_id = *self.key_manager_id.read().unwrap();
+
+        let consensus_state = self.consensus_verifier.latest_state().await?;
+        tokio::task::block_in_place(move || {
+            let beacon_state = BeaconState::new(&consensus_state);
+            let epoch = beacon_state.epoch()?;
+
+            self.caches
+                .statuses
+                .get_or_fetch(key_manager_id, runtime_id, epoch, || {
+                    let km_state = KeyManagerState::new(&consensus_state);
+                    Ok(km_state.status(runtime_id)?)
+                })
+        })
+    }
+
+    /// Subscribe to status changes of the given key manager runtime.
+    ///
+    /// The returned stream yields the current status followed by every status
+    /// subsequently set via `set_status` which supersedes the previous one.
+    pub async fn watch_status(
+        &self,
+        runtime_id: Namespace,
+    ) -> Result<BoxStream<'static, KeyManagerStatus>, KeyManagerError> {
+        let status = self.status(runtime_id).await?;
+        Ok(self.status_watchers.subscribe(status))
+    }
+
+    /// Close RPC sessions which have not been used for at least the given timeout.
+    ///
+    /// Closed sessions are re-established on the next request. Callers that keep the client
+    /// around while idle should call this periodically to release unused sessions.
+    pub async fn close_idle_sessions(&self, idle_session_timeout: Duration) {
+        let idle_timeout = idle_session_timeout
+            .as_secs()
+            .try_into()
+            .unwrap_or(i64::MAX);
+        self.rpc_client.close_idle_sessions(idle_timeout).await
+    }
+
+    /// Clear the given local cache.
+    pub fn invalidate(&self, kind: CacheKind) {
+        self.caches.invalidate(kind);
+    }
+
+    /// Clear all local caches and close all RPC sessions.
+    ///
+    /// Unlike `clear_cache`, this also clears the CHURP state keys and the key manager
+    /// statuses, so that the next request re-establishes a session, re-fetches the status
+    /// and recovers state keys from the key manager committee.
+    pub async fn invalidate_all(&self) {
+        self.caches.invalidate_all();
+        self.rpc_client.close_all_sessions().await;
+    }
+
     /// Set key manager's quote policy.
     pub async fn set_quote_policy(&self, policy: QuotePolicy) {
+        *self.quote_policy.write().unwrap() = Some(Arc::new(policy.clone()));
         self.rpc_client.update_quote_policy(policy).await;
     }
 
+    /// Verify that the given node is still registered in the consensus layer with a TEE
+    /// capability for the key manager runtime at the current epoch, and that its registered
+    /// attestation is still valid under the key manager's quote policy.
+    ///
+    /// Sessions are only attested once, when established, so this can be used to detect
+    /// nodes whose registration has expired or whose attestation has been withdrawn or has
+    /// lapsed since.
+    pub async fn verify_node_attestation_fresh(
+        &self,
+        node_id: PublicKey,
+    ) -> Result<(), KeyManagerError> {
+        let key_manager_id = self
+            .key_manager_id
+            .read()
+            .unwrap()
+            .ok_or(KeyManagerError::NotInitialized)?;
+        let quote_policy = self
+            .quote_policy
+            .read()
+            .unwrap()
+            .clone()
+            .ok_or(KeyManagerError::NotInitialized)?;
+
+        let consensus_state = self.consensus_verifier.latest_state().await?;
+        let (node, epoch) = tokio::task::block_in_place(move || -> Result<_, KeyManagerError> {
+            let registry_state = RegistryState::new(&consensus_state);
+            let node = registry_state.node(&node_id)?;
+
+            let beacon_state = BeaconState::new(&consensus_state);
+            let epoch = beacon_state.epoch()?;
+
+            Ok((node, epoch))
+        })?;
+
+        verify_node_attestation(
+            node_id,
+            node.as_ref(),
+            &key_manager_id,
+            epoch,
+            &quote_policy,
+        )
+    }
+
     fn verify_public_key(
         &self,
         key: &SignedPublicKey,
@@ -265,6 +472,9 @@ impl RemoteClient {
         let mut committee = status.committee;
         committee.shuffle(&mut OsRng);
 
+        // Abort early if the committee is too small to provide enough shares.
+        verify_committee_size(committee.len(), min_shares)?;
+
         // Fetch key shares concurrently.
         let mut futures = FuturesUnordered::new();
 
@@ -275,7 +485,13 @@ impl RemoteClient {
             while shares.len() + futures.len() < min_shares {
                 let node_id = match committee.pop() {
                     Some(node_id) => node_id,
-                    None => return Err(KeyManagerError::InsufficientKeyShares),
+                    None => {
+                        // Too many committee members failed to respond.
+                        return Err(KeyManagerError::CommitteeQuorumUnavailable {
+                            online: shares.len() + futures.len(),
+                            required: min_shares,
+                        });
+                    }
                 };
 
                 let future = self.rpc_client.secure_call(
@@ -348,23 +564,8 @@ impl KeyManagerClient for RemoteClient {
     }
 
     fn clear_cache(&self) {
-        // We explicitly only take one lock at a time.
-
-        let mut cache = self.longterm_private_keys.write().unwrap();
-        cache.clear();
-        drop(cache);
-
-        let mut cache = self.longterm_public_keys.write().unwrap();
-        cache.clear();
-        drop(cache);
-
-        let mut cache = self.ephemeral_private_keys.write().unwrap();
-        cache.clear();
-        drop(cache);
-
-        let mut cache = self.ephemeral_public_keys.write().unwrap();
-        cache.clear();
-        drop(cache);
+        self.invalidate(CacheKind::LongTermKeys);
+        self.invalidate(CacheKind::EphemeralKeys);
     }
 
     async fn get_or_create_keys(
@@ -376,7 +577,7 @@ impl KeyManagerClient for RemoteClient {
 
         // First try to fetch from cache.
         {
-            let mut cache = self.longterm_private_keys.write().unwrap();
+            let mut cache = self.caches.longterm_private_keys.write().unwrap();
             if let Some(keys) = cache.get(&id) {
                 return Ok(keys.clone());
             }
@@ -404,10 +605,10 @@ impl KeyManagerClient for RemoteClient {
             .await
             .into_result_with_feedback()
             .await
-            .map_err(|err| KeyManagerError::Other(err.into()))?;
+            .map_err(rpc_error)?;
 
         // Cache key.
-        let mut cache = self.longterm_private_keys.write().unwrap();
+        let mut cache = self.caches.longterm_private_keys.write().unwrap();
         cache.put(id, keys.clone());
 
         Ok(keys)
@@ -422,7 +623,7 @@ impl KeyManagerClient for RemoteClient {
 
         // First fetch from cache.
         {
-            let mut cache = self.longterm_public_keys.write().unwrap();
+            let mut cache = self.caches.longterm_public_keys.write().unwrap();
             if let Some(key) = cache.get(&id) {
                 match self.verify_public_key(key, key_pair_id, None, None) {
                     Ok(()) => return Ok(key.clone()),
@@ -455,13 +656,13 @@ impl KeyManagerClient for RemoteClient {
             .await
             .into_result_with_feedback()
             .await
-            .map_err(|err| KeyManagerError::Other(err.into()))?;
+            .map_err(rpc_error)?;
 
         // Verify the signature.
         self.verify_public_key(&key, key_pair_id, None, None)?;
 
         // Cache key.
-        let mut cache = self.longterm_public_keys.write().unwrap();
+        let mut cache = self.caches.longterm_public_keys.write().unwrap();
         cache.put(id, key.clone());
 
         Ok(key)
@@ -476,12 +677,19 @@ impl KeyManagerClient for RemoteClient {
 
         // First try to fetch from cache.
         {
-            let mut cache = self.ephemeral_private_keys.write().unwrap();
+            let mut cache = self.caches.ephemeral_private_keys.write().unwrap();
             if let Some(keys) = cache.get(&id) {
                 return Ok(keys.clone());
             }
         }
 
+        // Fetch current epoch.
+        let consensus_state = self.consensus_verifier.latest_state().await?;
+        let consensus_epoch = tokio::task::block_in_place(move || {
+            let beacon_state = BeaconState::new(&consensus_state);
+            beacon_state.epoch()
+        })?;
+
         // No entry in cache, fetch from key manager.
         let height = self
             .consensus_verifier
@@ -489,25 +697,26 @@ impl KeyManagerClient for RemoteClient {
             .await
             .map_err(|err| KeyManagerError::Other(err.into()))?;
 
-        let keys: KeyPair = self
-            .rpc_client
-            .secure_call(
-                METHOD_GET_OR_CREATE_EPHEMERAL_KEYS,
-                EphemeralKeyRequest {
-                    height: Some(height),
-                    runtime_id: self.runtime_id,
-                    key_pair_id,
-                    epoch,
-                },
-                vec![],
-            )
-            .await
-            .into_result_with_feedback()
-            .await
-            .map_err(|err| KeyManagerError::Other(err.into()))?;
+        let keys: KeyPair = poll_ephemeral_secret(epoch, consensus_epoch, || async move {
+            let response = self
+                .rpc_client
+                .secure_call(
+                    METHOD_GET_OR_CREATE_EPHEMERAL_KEYS,
+                    EphemeralKeyRequest {
+                        height: Some(height),
+                        runtime_id: self.runtime_id,
+                        key_pair_id,
+                        epoch,
+                    },
+                    vec![],
+                )
+                .await;
+            into_ephemeral_key_result(response).await
+        })
+        .await?;
 
         // Cache key.
-        let mut cache = self.ephemeral_private_keys.write().unwrap();
+        let mut cache = self.caches.ephemeral_private_keys.write().unwrap();
         cache.put(id, keys.clone());
 
         Ok(keys)
@@ -529,7 +738,7 @@ impl KeyManagerClient for RemoteClient {
 
         // First try to fetch from cache.
         {
-            let mut cache = self.ephemeral_public_keys.write().unwrap();
+            let mut cache = self.caches.ephemeral_public_keys.write().unwrap();
             if let Some(key) = cache.get(&id) {
                 match self.verify_public_key(key, key_pair_id, Some(epoch), Some(consensus_epoch)) {
                     Ok(()) => return Ok(key.clone()),
@@ -547,28 +756,29 @@ impl KeyManagerClient for RemoteClient {
             .await
             .map_err(|err| KeyManagerError::Other(err.into()))?;
 
-        let key: SignedPublicKey = self
-            .rpc_client
-            .insecure_call(
-                METHOD_GET_PUBLIC_EPHEMERAL_KEY,
-                EphemeralKeyRequest {
-                    height: Some(height),
-                    runtime_id: self.runtime_id,
-                    key_pair_id,
-                    epoch,
-                },
-                vec![],
-            )
-            .await
-            .into_result_with_feedback()
-            .await
-            .map_err(|err| KeyManagerError::Other(err.into()))?;
+        let key: SignedPublicKey = poll_ephemeral_secret(epoch, consensus_epoch, || async move {
+            let response = self
+                .rpc_client
+                .insecure_call(
+                    METHOD_GET_PUBLIC_EPHEMERAL_KEY,
+                    EphemeralKeyRequest {
+                        height: Some(height),
+                        runtime_id: self.runtime_id,
+                        key_pair_id,
+                        epoch,
+                    },
+                    vec![],
+                )
+                .await;
+            into_ephemeral_key_result(response).await
+        })
+        .await?;
 
         // Verify the signature.
         self.verify_public_key(&key, key_pair_id, Some(epoch), Some(consensus_epoch))?;
 
         // Cache key.
-        let mut cache = self.ephemeral_public_keys.write().unwrap();
+        let mut cache = self.caches.ephemeral_public_keys.write().unwrap();
         cache.put(id, key.clone());
 
         Ok(key)
@@ -597,7 +807,7 @@ impl KeyManagerClient for RemoteClient {
             .await
             .into_result_with_feedback()
             .await
-            .map_err(|err| KeyManagerError::Other(err.into()))
+            .map_err(rpc_error)
             .map(|rsp: ReplicateMasterSecretResponse| VerifiableSecret {
                 secret: rsp.master_secret,
                 checksum: rsp.checksum,
@@ -627,7 +837,7 @@ impl KeyManagerClient for RemoteClient {
             .await
             .into_result_with_feedback()
             .await
-            .map_err(|err| KeyManagerError::Other(err.into()))
+            .map_err(rpc_error)
             .map(|rsp: ReplicateEphemeralSecretResponse| rsp.ephemeral_secret)
     }
 
@@ -651,7 +861,7 @@ impl KeyManagerClient for RemoteClient {
             .await
             .into_result_with_feedback()
             .await
-            .map_err(|err| KeyManagerError::Other(err.into()))
+            .map_err(rpc_error)
     }
 
     async fn churp_share_reduction_point(
@@ -675,7 +885,7 @@ impl KeyManagerClient for RemoteClient {
             .await
             .into_result_with_feedback()
             .await
-            .map_err(|err| KeyManagerError::Other(err.into()))
+            .map_err(rpc_error)
     }
 
     async fn churp_share_distribution_point(
@@ -699,7 +909,7 @@ impl KeyManagerClient for RemoteClient {
             .await
             .into_result_with_feedback()
             .await
-            .map_err(|err| KeyManagerError::Other(err.into()))
+            .map_err(rpc_error)
     }
 
     async fn churp_bivariate_share(
@@ -723,7 +933,7 @@ impl KeyManagerClient for RemoteClient {
             .await
             .into_result_with_feedback()
             .await
-            .map_err(|err| KeyManagerError::Other(err.into()))
+            .map_err(rpc_error)
     }
 
     async fn churp_state_key(
@@ -735,7 +945,7 @@ impl KeyManagerClient for RemoteClient {
 
         // First try to fetch from cache.
         {
-            let mut cache = self.state_keys.write().unwrap();
+            let mut cache = self.caches.state_keys.write().unwrap();
             if let Some(key) = cache.get(&id) {
                 return Ok(key.clone());
             }
@@ -767,9 +977,485 @@ impl KeyManagerClient for RemoteClient {
         };
 
         // Cache key.
-        let mut cache = self.state_keys.write().unwrap();
+        let mut cache = self.caches.state_keys.write().unwrap();
         cache.put(id, state_key.clone());
 
         Ok(state_key)
     }
 }
+
+/// Verify that the node's registration has not expired and that the attestation of its
+/// TEE capability for the given runtime is still valid under the given quote policy.
+fn verify_node_attestation(
+    node_id: PublicKey,
+    node: Option<&Node>,
+    runtime_id: &Namespace,
+    epoch: EpochTime,
+    quote_policy: &QuotePolicy,
+) -> Result<(), KeyManagerError> {
+    let tee = registered_tee(node_id, node, runtime_id, epoch)?;
+    tee.verify(quote_policy, &node_id)
+        .map_err(|_| KeyManagerError::NodeAttestationStale(node_id))?;
+
+    Ok(())
+}
+
+/// Return the TEE capability the node registered for the given runtime, if its
+/// registration has not expired.
+fn registered_tee<'a>(
+    node_id: PublicKey,
+    node: Option<&'a Node>,
+    runtime_id: &Namespace,
+    epoch: EpochTime,
+) -> Result<&'a CapabilityTEE, KeyManagerError> {
+    let node = node.ok_or(KeyManagerError::NodeAttestationStale(node_id))?;
+    if node.expiration < epoch {
+        return Err(KeyManagerError::NodeAttestationStale(node_id));
+    }
+
+    // Skipping version check as key managers are running exactly
+    // one version of the runtime.
+    node.runtimes
+        .as_ref()
+        .into_iter()
+        .flatten()
+        .find(|nr| &nr.id == runtime_id)
+        .and_then(|nr| nr.capabilities.tee.as_ref())
+        .ok_or(KeyManagerError::NodeAttestationStale(node_id))
+}
+
+/// Convert an RPC client error into a key manager error, so that errors caused by
+/// an unreachable key manager can be told apart from errors returned by it.
+fn rpc_error(err: RpcClientError) -> KeyManagerError {
+    match err {
+        RpcClientError::Transport | RpcClientError::Dropped | RpcClientError::SessionsError(_) => {
+            KeyManagerError::Unreachable(err.to_string())
+        }
+        RpcClientError::CallFailed(msg) => call_error(msg),
+        err => KeyManagerError::Other(err.into()),
+    }
+}
+
+/// Decode the error returned by the key manager, so that errors the client acts upon
+/// keep their type. Other errors are returned as they are.
+///
+/// Errors are only transferred as their messages, so the candidate error is rebuilt from
+/// the numbers found in the message and accepted only if it renders the same message.
+fn call_error(msg: String) -> KeyManagerError {
+    let numbers: Vec<u64> = msg
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|number| number.parse().ok())
+        .collect();
+
+    let candidate = match numbers[..] {
+        [] => Some(KeyManagerError::EphemeralSecretNotPublished),
+        [epoch] => Some(KeyManagerError::EphemeralSecretNotFound(epoch)),
+        [last, got] => Some(KeyManagerError::GenerationFromFuture(last, got)),
+        _ => None,
+    };
+
+    match candidate {
+        Some(err) if err.to_string() == msg => err,
+        _ => KeyManagerError::Other(RpcClientError::CallFailed(msg).into()),
+    }
+}
+
+/// Return the result of the given ephemeral key response, sending back peer feedback
+/// unless the ephemeral secret was not available.
+///
+/// Such requests are polled around epoch transitions, when the secret may not have been
+/// published yet, so reporting them as failures would penalize healthy key manager nodes.
+async fn into_ephemeral_key_result<T>(response: Response<'_, T>) -> Result<T, KeyManagerError> {
+    if matches!(response.result(), Err(err) if is_ephemeral_secret_missing(err)) {
+        return response.into_result().map_err(rpc_error);
+    }
+    response
+        .into_result_with_feedback()
+        .await
+        .map_err(rpc_error)
+}
+
+/// Whether the key manager refused the request because the ephemeral secret is not available.
+fn is_ephemeral_secret_missing(err: &RpcClientError) -> bool {
+    match err {
+        RpcClientError::CallFailed(msg) => matches!(
+            call_error(msg.clone()),
+            KeyManagerError::EphemeralSecretNotFound(_)
+                | KeyManagerError::EphemeralSecretNotPublished
+        ),
+        _ => false,
+    }
+}
+
+/// Request ephemeral keys for the given epoch, retrying with a short backoff while the
+/// ephemeral secret is not yet available.
+///
+/// Ephemeral secrets are published around epoch transitions, so requests for the current
+/// or the next epoch can race with their publication. Such requests are retried for
+/// a bounded time window. Requests for past epochs fail immediately, as their secrets
+/// will never become available.
+async fn poll_ephemeral_secret<T, F, Fut>(
+    epoch: EpochTime,
+    consensus_epoch: EpochTime,
+    request: F,
+) -> Result<T, KeyManagerError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, KeyManagerError>>,
+{
+    let window = if epoch >= consensus_epoch {
+        EPHEMERAL_SECRET_POLL_WINDOW
+    } else {
+        Duration::ZERO
+    };
+
+    poll_with_backoff(window, EPHEMERAL_SECRET_POLL_INTERVAL, request).await
+}
+
+async fn poll_with_backoff<T, F, Fut>(
+    window: Duration,
+    interval: Duration,
+    request: F,
+) -> Result<T, KeyManagerError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, KeyManagerError>>,
+{
+    let deadline = tokio::time::Instant::now() + window;
+    let mut delay = interval;
+
+    loop {
+        match request().await {
+            Err(
+                KeyManagerError::EphemeralSecretNotFound(_)
+                | KeyManagerError::EphemeralSecretNotPublished,
+            ) if tokio::time::Instant::now() + delay <= deadline => {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Verify that the committee is large enough to provide the required number of key shares.
+fn verify_committee_size(size: usize, required: usize) -> Result<(), KeyManagerError> {
+    if size < required {
+        return Err(KeyManagerError::InsufficientKeyShares);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+
+    use oasis_core_runtime::{
+        common::{
+            crypto::x25519,
+            sgx::{self, ias},
+        },
+        consensus::registry::{Capabilities, NodeRuntime, SGXAttestation, TEEHardware},
+        enclave_rpc::sessions,
+    };
+
+    use super::*;
+
+    fn node(runtime_id: Namespace, expiration: EpochTime) -> Node {
+        Node {
+            expiration,
+            runtimes: Some(vec![NodeRuntime {
+                id: runtime_id,
+                capabilities: Capabilities {
+                    tee: Some(CapabilityTEE::default()),
+                },
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_verify_node_attestation_fresh() {
+        let node_id = PublicKey::from(vec![1; 32]);
+        let runtime_id = Namespace::from(vec![2; 32]);
+        let node = node(runtime_id, 10);
+        let tee = &node.runtimes.as_ref().unwrap()[0].capabilities.tee;
+
+        let result = registered_tee(node_id, Some(&node), &runtime_id, 9);
+        assert_eq!(Some(result.expect("node should be fresh")), tee.as_ref());
+        let result = registered_tee(node_id, Some(&node), &runtime_id, 10);
+        assert_eq!(
+            Some(result.expect("node should be fresh until expiration")),
+            tee.as_ref()
+        );
+    }
+
+    #[test]
+    fn test_verify_node_attestation_stale() {
+        let node_id = PublicKey::from(vec![1; 32]);
+        let runtime_id = Namespace::from(vec![2; 32]);
+        let expected = KeyManagerError::NodeAttestationStale(node_id).to_string();
+
+        // Node not registered.
+        let result = registered_tee(node_id, None, &runtime_id, 1);
+        assert_eq!(result.unwrap_err().to_string(), expected);
+
+        // Registration expired.
+        let stale = node(runtime_id, 10);
+        let result = registered_tee(node_id, Some(&stale), &runtime_id, 11);
+        assert_eq!(result.unwrap_err().to_string(), expected);
+
+        // Attestation withdrawn.
+        let mut revoked = node(runtime_id, 10);
+        revoked.runtimes.as_mut().unwrap()[0].capabilities.tee = None;
+        let result = registered_tee(node_id, Some(&revoked), &runtime_id, 1);
+        assert_eq!(result.unwrap_err().to_string(), expected);
+
+        // Not registered for the key manager runtime.
+        let other = node(Namespace::from(vec![3; 32]), 10);
+        let result = registered_tee(node_id, Some(&other), &runtime_id, 1);
+        assert_eq!(result.unwrap_err().to_string(), expected);
+    }
+
+    #[test]
+    fn test_verify_node_attestation_invalid_quote() {
+        let node_id = PublicKey::from(vec![1; 32]);
+        let runtime_id = Namespace::from(vec![2; 32]);
+        let policy = QuotePolicy::default();
+        let expected = KeyManagerError::NodeAttestationStale(node_id).to_string();
+
+        // Registration has not expired, but the attestation is not an SGX one.
+        let mut invalid = node(runtime_id, 10);
+        let result = verify_node_attestation(node_id, Some(&invalid), &runtime_id, 1, &policy);
+        assert_eq!(result.unwrap_err().to_string(), expected);
+
+        // Registration has not expired, but the quote is not valid (anymore).
+        let attestation = SGXAttestation::V1 {
+            quote: sgx::Quote::Ias(ias::AVR::default()),
+            height: 1,
+            signature: Default::default(),
+        };
+        let tee = invalid.runtimes.as_mut().unwrap()[0]
+            .capabilities
+            .tee
+            .as_mut()
+            .unwrap();
+        tee.hardware = TEEHardware::TEEHardwareIntelSGX;
+        tee.rek = Some(x25519::PublicKey::from([1; 32]));
+        tee.attestation = cbor::to_vec(attestation);
+        let result = verify_node_attestation(node_id, Some(&invalid), &runtime_id, 1, &policy);
+        assert_eq!(result.unwrap_err().to_string(), expected);
+
+        // Registration-level checks come first.
+        let result = verify_node_attestation(node_id, None, &runtime_id, 1, &policy);
+        assert_eq!(result.unwrap_err().to_string(), expected);
+    }
+
+    #[test]
+    fn test_rpc_error() {
+        assert!(rpc_error(RpcClientError::Transport).is_transient());
+        assert!(rpc_error(RpcClientError::Dropped).is_transient());
+        assert!(rpc_error(RpcClientError::SessionsError(
+            sessions::Error::MaxConcurrentSessions
+        ))
+        .is_transient());
+
+        let err = rpc_error(RpcClientError::CallFailed(
+            "client is not authorized".into(),
+        ));
+        assert!(!err.is_transient());
+        assert!(matches!(err, KeyManagerError::Other(_)));
+
+        // Errors the client acts upon keep their type.
+        let msg = KeyManagerError::GenerationFromFuture(3, 5).to_string();
+        let err = rpc_error(RpcClientError::CallFailed(msg));
+        assert!(matches!(err, KeyManagerError::GenerationFromFuture(3, 5)));
+
+        let msg = "generation is in the future: expected max 3, got x".to_string();
+        let err = rpc_error(RpcClientError::CallFailed(msg));
+        assert!(matches!(err, KeyManagerError::Other(_)));
+
+        let msg = KeyManagerError::InvalidGeneration(3, 5).to_string();
+        let err = rpc_error(RpcClientError::CallFailed(msg));
+        assert!(matches!(err, KeyManagerError::Other(_)));
+
+        let msg = KeyManagerError::EphemeralSecretNotFound(7).to_string();
+        let err = rpc_error(RpcClientError::CallFailed(msg));
+        assert!(matches!(err, KeyManagerError::EphemeralSecretNotFound(7)));
+
+        let msg = KeyManagerError::EphemeralSecretNotPublished.to_string();
+        let err = rpc_error(RpcClientError::CallFailed(msg));
+        assert!(matches!(err, KeyManagerError::EphemeralSecretNotPublished));
+    }
+
+    #[test]
+    fn test_is_ephemeral_secret_missing() {
+        let err =
+            RpcClientError::CallFailed(KeyManagerError::EphemeralSecretNotFound(7).to_string());
+        assert!(is_ephemeral_secret_missing(&err));
+        let err =
+            RpcClientError::CallFailed(KeyManagerError::EphemeralSecretNotPublished.to_string());
+        assert!(is_ephemeral_secret_missing(&err));
+
+        // Other failures are reported as such.
+        let err = RpcClientError::CallFailed(KeyManagerError::NotAuthorized.to_string());
+        assert!(!is_ephemeral_secret_missing(&err));
+        assert!(!is_ephemeral_secret_missing(&RpcClientError::Transport));
+    }
+
+    #[test]
+    fn test_poll_with_backoff() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let window = Duration::from_secs(60);
+        let interval = Duration::from_millis(1);
+
+        // Secret published shortly after the first request.
+        let calls = Cell::new(0);
+        let result = rt.block_on(poll_with_backoff(window, interval, || {
+            calls.set(calls.get() + 1);
+            let result = match calls.get() {
+                1 => Err(KeyManagerError::EphemeralSecretNotFound(1)),
+                _ => Ok(1),
+            };
+            async move { result }
+        }));
+        assert_eq!(result.expect("retry should succeed"), 1);
+        assert_eq!(calls.get(), 2);
+
+        // Secret not published within the window.
+        let calls = Cell::new(0);
+        let result: Result<(), _> =
+            rt.block_on(poll_with_backoff(Duration::ZERO, interval, || {
+                calls.set(calls.get() + 1);
+                async { Err(KeyManagerError::EphemeralSecretNotPublished) }
+            }));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::EphemeralSecretNotPublished.to_string()
+        );
+        assert_eq!(calls.get(), 1);
+
+        // Secrets of past epochs are never retried.
+        let calls = Cell::new(0);
+        let result: Result<(), _> = rt.block_on(poll_ephemeral_secret(1, 3, || {
+            calls.set(calls.get() + 1);
+            async { Err(KeyManagerError::EphemeralSecretNotFound(1)) }
+        }));
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+
+        // Other errors are not retried.
+        let calls = Cell::new(0);
+        let result: Result<(), _> = rt.block_on(poll_with_backoff(window, interval, || {
+            calls.set(calls.get() + 1);
+            async { Err(KeyManagerError::NotAuthorized) }
+        }));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::NotAuthorized.to_string()
+        );
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_invalidate_caches() {
+        let caches = Caches::new(NonZeroUsize::new(2).unwrap());
+        let runtime_id = Namespace::from(vec![1; 32]);
+        let key_pair_id = KeyPairId::from(vec![1; 32]);
+        let fetches = Cell::new(0);
+        let fetch_status = || {
+            caches
+                .statuses
+                .get_or_fetch(None, runtime_id, 1, || {
+                    fetches.set(fetches.get() + 1);
+                    Ok(Some(KeyManagerStatus {
+                        id: runtime_id,
+                        ..Default::default()
+                    }))
+                })
+                .expect("status should be fetched");
+        };
+        let fill = || {
+            let id = (key_pair_id, 1);
+            caches
+                .longterm_private_keys
+                .write()
+                .unwrap()
+                .put(id, Default::default());
+            caches
+                .longterm_public_keys
+                .write()
+                .unwrap()
+                .put(id, Default::default());
+            caches
+                .ephemeral_private_keys
+                .write()
+                .unwrap()
+                .put(id, Default::default());
+            caches
+                .ephemeral_public_keys
+                .write()
+                .unwrap()
+                .put(id, Default::default());
+            caches
+                .state_keys
+                .write()
+                .unwrap()
+                .put((key_pair_id, 1), Default::default());
+            fetch_status();
+        };
+        let key_caches_empty = || {
+            [
+                caches.longterm_private_keys.read().unwrap().is_empty(),
+                caches.longterm_public_keys.read().unwrap().is_empty(),
+                caches.ephemeral_private_keys.read().unwrap().is_empty(),
+                caches.ephemeral_public_keys.read().unwrap().is_empty(),
+                caches.state_keys.read().unwrap().is_empty(),
+            ]
+        };
+
+        fill();
+        assert_eq!(fetches.get(), 1);
+        fetch_status();
+        assert_eq!(fetches.get(), 1);
+
+        // Statuses only.
+        caches.invalidate(CacheKind::Statuses);
+        assert_eq!(key_caches_empty(), [false; 5]);
+        fetch_status();
+        assert_eq!(fetches.get(), 2);
+
+        // Long-term keys only.
+        caches.invalidate(CacheKind::LongTermKeys);
+        assert_eq!(key_caches_empty(), [true, true, false, false, false]);
+
+        // All caches.
+        fill();
+        caches.invalidate_all();
+        assert_eq!(key_caches_empty(), [true; 5]);
+        fetch_status();
+        assert_eq!(fetches.get(), 3);
+    }
+
+    #[test]
+    fn test_verify_committee_size() {
+        verify_committee_size(3, 2).expect("committee should be large enough");
+        verify_committee_size(2, 2).expect("committee should be large enough");
+
+        let err = verify_committee_size(1, 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            KeyManagerError::InsufficientKeyShares.to_string()
+        );
+        assert!(!err.is_transient());
+        assert!(KeyManagerError::CommitteeQuorumUnavailable {
+            online: 1,
+            required: 2
+        }
+        .is_transient());
+    }
+}
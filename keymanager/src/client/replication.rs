@@ -0,0 +1,134 @@
+//! Replication of master secrets over a range of generations.
+use std::future::Future;
+
+use futures::stream::{self, BoxStream, StreamExt};
+
+use oasis_core_runtime::common::crypto::signature::PublicKey;
+
+use crate::{api::KeyManagerError, crypto::VerifiableSecret};
+
+use super::KeyManagerClient;
+
+/// Result of replicating the master secret for a single generation.
+pub type GenerationReplicationResult = (u64, Result<VerifiableSecret, KeyManagerError>);
+
+/// Replicate master secrets for all generations in the inclusive range `from..=to`.
+///
+/// The returned stream yields a result for each generation, in ascending order, as soon
+/// as it is available. At most `concurrency` requests are in flight at any time. The stream
+/// ends after the first generation which the key manager reports as being from the future,
+/// as none of the following generations can be replicated either.
+pub fn replicate_generations_stream<C>(
+    client: C,
+    from: u64,
+    to: u64,
+    nodes: Vec<PublicKey>,
+    concurrency: usize,
+) -> BoxStream<'static, GenerationReplicationResult>
+where
+    C: KeyManagerClient + Clone + 'static,
+{
+    replicate_with(from, to, concurrency, move |generation| {
+        let client = client.clone();
+        let nodes = nodes.clone();
+        async move { client.replicate_master_secret(generation, nodes).await }
+    })
+}
+
+fn replicate_with<F, Fut>(
+    from: u64,
+    to: u64,
+    concurrency: usize,
+    replicate: F,
+) -> BoxStream<'static, GenerationReplicationResult>
+where
+    F: Fn(u64) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<VerifiableSecret, KeyManagerError>> + Send + 'static,
+{
+    let results = stream::iter(from..=to)
+        .map(move |generation| {
+            let fut = replicate(generation);
+            async move { (generation, fut.await) }
+        })
+        .buffered(concurrency.max(1));
+
+    // End the stream right after the first generation from the future, dropping
+    // any requests still in flight instead of waiting for them.
+    stream::unfold(Some(results), |results| async move {
+        let mut results = results?;
+        let item = results.next().await?;
+        let results = match item.1 {
+            Err(KeyManagerError::GenerationFromFuture(..)) => None,
+            _ => Some(results),
+        };
+        Some((item, results))
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use futures::{executor::block_on, future, StreamExt};
+
+    use crate::{
+        api::KeyManagerError,
+        crypto::{Secret, VerifiableSecret, SECRET_SIZE},
+    };
+
+    use super::replicate_with;
+
+    #[test]
+    fn test_replicate_generations_stream() {
+        let last_generation = 3;
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let requested_clone = requested.clone();
+        let stream = replicate_with(0, 6, 2, move |generation| {
+            requested_clone.lock().unwrap().push(generation);
+            async move {
+                // Requests for generations after the first one from the future never complete.
+                if generation > last_generation + 1 {
+                    future::pending::<()>().await;
+                }
+                match generation {
+                    1 => Err(KeyManagerError::MasterSecretNotFound(generation)),
+                    g if g > last_generation => Err(KeyManagerError::GenerationFromFuture(
+                        last_generation,
+                        generation,
+                    )),
+                    _ => Ok(VerifiableSecret {
+                        secret: Secret([generation as u8; SECRET_SIZE]),
+                        checksum: vec![generation as u8],
+                    }),
+                }
+            }
+        });
+        let items: Vec<_> = block_on(stream.collect());
+
+        // The stream ends without waiting for requests still in flight, and no further
+        // requests are issued.
+        assert!(!requested.lock().unwrap().contains(&6));
+
+        // Generations following the first one from the future are not reported.
+        let generations: Vec<_> = items.iter().map(|(g, _)| *g).collect();
+        assert_eq!(generations, vec![0, 1, 2, 3, 4]);
+
+        for (generation, result) in items {
+            match generation {
+                0 | 2 | 3 => {
+                    let secret = result.expect("replication should succeed");
+                    assert_eq!(secret.checksum, vec![generation as u8]);
+                }
+                1 => assert_eq!(
+                    result.unwrap_err().to_string(),
+                    KeyManagerError::MasterSecretNotFound(1).to_string()
+                ),
+                _ => assert_eq!(
+                    result.unwrap_err().to_string(),
+                    KeyManagerError::GenerationFromFuture(3, 4).to_string()
+                ),
+            }
+        }
+    }
+}
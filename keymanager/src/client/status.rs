@@ -0,0 +1,323 @@
+//! Key manager status cache and watchers.
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::{Mutex, RwLock},
+};
+
+use futures::{
+    channel::mpsc,
+    stream::{BoxStream, StreamExt},
+};
+
+use oasis_core_runtime::{
+    common::namespace::Namespace,
+    consensus::{beacon::EpochTime, state::keymanager::Status as KeyManagerStatus},
+};
+
+use crate::api::KeyManagerError;
+
+/// Cache of key manager statuses, keyed by key manager runtime ID.
+///
+/// Statuses can only change at epoch transitions, so cached entries are valid
+/// for the epoch in which they were fetched.
+#[derive(Default)]
+pub(super) struct StatusCache {
+    statuses: RwLock<HashMap<Namespace, (EpochTime, KeyManagerStatus)>>,
+}
+
+impl StatusCache {
+    /// Create a new empty status cache.
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the status of the given key manager runtime for the given epoch, fetching
+    /// it if not cached or if the cached entry is from a different epoch.
+    ///
+    /// If the expected key manager runtime ID is known, requesting the status of any other
+    /// runtime fails with `RuntimeMismatch`.
+    pub(super) fn get_or_fetch<F>(
+        &self,
+        expected_id: Option<Namespace>,
+        runtime_id: Namespace,
+        epoch: EpochTime,
+        fetch: F,
+    ) -> Result<KeyManagerStatus, KeyManagerError>
+    where
+        F: FnOnce() -> Result<Option<KeyManagerStatus>, KeyManagerError>,
+    {
+        if let Some(expected_id) = expected_id {
+            if expected_id != runtime_id {
+                return Err(KeyManagerError::RuntimeMismatch {
+                    expected: expected_id,
+                    got: runtime_id,
+                });
+            }
+        }
+
+        // First try to fetch from cache.
+        {
+            let statuses = self.statuses.read().unwrap();
+            if let Some((cached_epoch, status)) = statuses.get(&runtime_id) {
+                if *cached_epoch == epoch {
+                    return Ok(status.clone());
+                }
+            }
+        }
+
+        // No valid entry in cache, fetch from consensus.
+        let status = fetch()?.ok_or(KeyManagerError::StatusNotFound)?;
+
+        let mut statuses = self.statuses.write().unwrap();
+        statuses.insert(runtime_id, (epoch, status.clone()));
+
+        Ok(status)
+    }
+
+    /// Remove the cached status of the given key manager runtime.
+    pub(super) fn invalidate(&self, runtime_id: &Namespace) {
+        self.statuses.write().unwrap().remove(runtime_id);
+    }
+
+    /// Remove all cached statuses.
+    pub(super) fn clear(&self) {
+        self.statuses.write().unwrap().clear();
+    }
+}
+
+/// Latest known status of a key manager runtime and its subscribers.
+struct WatchedStatus {
+    status: KeyManagerStatus,
+    subscribers: Vec<mpsc::UnboundedSender<KeyManagerStatus>>,
+}
+
+/// Subscribers to key manager status changes, keyed by key manager runtime ID.
+#[derive(Default)]
+pub(super) struct StatusWatchers {
+    watched: Mutex<HashMap<Namespace, WatchedStatus>>,
+}
+
+impl StatusWatchers {
+    /// Create a new set of status watchers.
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribe to status changes of the key manager runtime the given status belongs to.
+    ///
+    /// The returned stream first yields the latest known status, followed by every subsequent
+    /// change. If the given status supersedes the latest known one, existing subscribers
+    /// are notified and the given status is yielded first.
+    pub(super) fn subscribe(
+        &self,
+        status: KeyManagerStatus,
+    ) -> BoxStream<'static, KeyManagerStatus> {
+        let (tx, rx) = mpsc::unbounded();
+
+        let mut watched = self.watched.lock().unwrap();
+        let entry = match watched.entry(status.id) {
+            Entry::Occupied(entry) => {
+                let entry = entry.into_mut();
+                if supersedes(&status, &entry.status) {
+                    // Drop subscribers whose streams have been dropped.
+                    entry
+                        .subscribers
+                        .retain(|tx| tx.unbounded_send(status.clone()).is_ok());
+                    entry.status = status;
+                }
+                entry
+            }
+            Entry::Vacant(entry) => entry.insert(WatchedStatus {
+                status,
+                subscribers: Vec::new(),
+            }),
+        };
+
+        // The receiver is still in scope, so this cannot fail.
+        let _ = tx.unbounded_send(entry.status.clone());
+        entry.subscribers.push(tx);
+
+        rx.boxed()
+    }
+
+    /// Record the given status and notify subscribers if it supersedes the latest known one.
+    ///
+    /// Returns true iff the status changed. Statuses older than the latest known one are
+    /// ignored, so that replayed statuses cannot move subscribers backwards.
+    pub(super) fn notify(&self, status: &KeyManagerStatus) -> bool {
+        let mut watched = self.watched.lock().unwrap();
+        match watched.get_mut(&status.id) {
+            Some(entry) if !supersedes(status, &entry.status) => false,
+            Some(entry) => {
+                entry.status = status.clone();
+                // Drop subscribers whose streams have been dropped.
+                entry
+                    .subscribers
+                    .retain(|tx| tx.unbounded_send(status.clone()).is_ok());
+                true
+            }
+            None => {
+                watched.insert(
+                    status.id,
+                    WatchedStatus {
+                        status: status.clone(),
+                        subscribers: Vec::new(),
+                    },
+                );
+                true
+            }
+        }
+    }
+}
+
+/// Whether the given status is newer than the other status of the same key manager,
+/// i.e. whether it has a later master secret generation, rotation or policy.
+fn is_newer(status: &KeyManagerStatus, other: &KeyManagerStatus) -> bool {
+    let version = |status: &KeyManagerStatus| {
+        let serial = status.policy.as_ref().map(|policy| policy.policy.serial);
+        (status.generation, status.rotation_epoch, serial)
+    };
+    version(status) > version(other)
+}
+
+/// Whether the given status should replace the other status of the same key manager,
+/// i.e. whether it differs from it without being older.
+///
+/// Statuses with the same generation, rotation and policy can still differ, e.g. when
+/// the committee changes.
+fn supersedes(status: &KeyManagerStatus, other: &KeyManagerStatus) -> bool {
+    status != other && !is_newer(other, status)
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+
+    use futures::{executor::block_on, StreamExt};
+
+    use oasis_core_runtime::common::crypto::signature::PublicKey;
+
+    use super::*;
+
+    fn status(runtime_id: Namespace, generation: u64) -> KeyManagerStatus {
+        KeyManagerStatus {
+            id: runtime_id,
+            generation,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_status_cache() {
+        let cache = StatusCache::new();
+        let runtime_id = Namespace::from(vec![1; 32]);
+        let fetches = Cell::new(0);
+        let fetch = |generation| -> Result<Option<KeyManagerStatus>, KeyManagerError> {
+            fetches.set(fetches.get() + 1);
+            Ok(Some(status(runtime_id, generation)))
+        };
+
+        // Cache miss.
+        let result = cache
+            .get_or_fetch(Some(runtime_id), runtime_id, 1, || fetch(1))
+            .expect("status should be fetched");
+        assert_eq!(result.generation, 1);
+        assert_eq!(fetches.get(), 1);
+
+        // Cache hit.
+        let result = cache
+            .get_or_fetch(Some(runtime_id), runtime_id, 1, || fetch(2))
+            .expect("status should be cached");
+        assert_eq!(result.generation, 1);
+        assert_eq!(fetches.get(), 1);
+
+        // Invalidation on epoch change.
+        let result = cache
+            .get_or_fetch(None, runtime_id, 2, || fetch(2))
+            .expect("status should be fetched");
+        assert_eq!(result.generation, 2);
+        assert_eq!(fetches.get(), 2);
+
+        // Clear.
+        cache.clear();
+        let result = cache
+            .get_or_fetch(None, runtime_id, 2, || fetch(3))
+            .expect("status should be fetched");
+        assert_eq!(result.generation, 3);
+        assert_eq!(fetches.get(), 3);
+    }
+
+    #[test]
+    fn test_status_cache_errors() {
+        let cache = StatusCache::new();
+        let runtime_id = Namespace::from(vec![1; 32]);
+        let other_id = Namespace::from(vec![2; 32]);
+
+        // Wrong runtime.
+        let result = cache.get_or_fetch(Some(runtime_id), other_id, 1, || {
+            panic!("status should not be fetched")
+        });
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::RuntimeMismatch {
+                expected: runtime_id,
+                got: other_id,
+            }
+            .to_string()
+        );
+
+        // Status not found.
+        let result = cache.get_or_fetch(Some(runtime_id), runtime_id, 1, || Ok(None));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::StatusNotFound.to_string()
+        );
+    }
+
+    #[test]
+    fn test_status_watchers() {
+        let watchers = StatusWatchers::new();
+        let runtime_id = Namespace::from(vec![1; 32]);
+        let other_id = Namespace::from(vec![2; 32]);
+
+        let stream = watchers.subscribe(status(runtime_id, 1));
+
+        // Only changes of the watched runtime are emitted.
+        assert!(!watchers.notify(&status(runtime_id, 1)));
+        assert!(watchers.notify(&status(other_id, 5)));
+        assert!(watchers.notify(&status(runtime_id, 2)));
+        assert!(!watchers.notify(&status(runtime_id, 2)));
+        assert!(watchers.notify(&status(runtime_id, 3)));
+
+        // Older statuses are ignored.
+        assert!(!watchers.notify(&status(runtime_id, 2)));
+
+        let generations: Vec<_> = block_on(stream.take(3).map(|s| s.generation).collect());
+        assert_eq!(generations, vec![1, 2, 3]);
+
+        // New subscribers start with the latest known status.
+        let stream = watchers.subscribe(status(runtime_id, 1));
+        let generations: Vec<_> = block_on(stream.take(1).map(|s| s.generation).collect());
+        assert_eq!(generations, vec![3]);
+
+        // Newer statuses given on subscription are recorded and sent to existing subscribers.
+        let stream = watchers.subscribe(status(runtime_id, 3));
+        let new_stream = watchers.subscribe(status(runtime_id, 4));
+        let generations: Vec<_> = block_on(stream.take(2).map(|s| s.generation).collect());
+        assert_eq!(generations, vec![3, 4]);
+        let generations: Vec<_> = block_on(new_stream.take(1).map(|s| s.generation).collect());
+        assert_eq!(generations, vec![4]);
+        assert!(!watchers.notify(&status(runtime_id, 4)));
+
+        // Committee changes are recorded even if the generation stays the same.
+        let mut changed = status(runtime_id, 4);
+        changed.nodes = vec![PublicKey::from(vec![1; 32])];
+        assert!(watchers.notify(&changed));
+        assert!(!watchers.notify(&status(runtime_id, 3)));
+
+        // Dropped subscribers are removed.
+        assert!(watchers.notify(&status(runtime_id, 5)));
+        let watched = watchers.watched.lock().unwrap();
+        assert!(watched.get(&runtime_id).unwrap().subscribers.is_empty());
+    }
+}
@@ -293,7 +293,11 @@ impl Inner {
             .as_ref()
             .ok_or(KeyManagerError::NotInitialized)?;
         if runtime_id != id {
-            return Err(KeyManagerError::RuntimeMismatch.into());
+            return Err(KeyManagerError::RuntimeMismatch {
+                expected: *id,
+                got: *runtime_id,
+            }
+            .into());
         }
         Ok(())
     }
@@ -301,7 +305,13 @@ impl Inner {
     pub fn set_runtime_id(&mut self, runtime_id: Namespace) -> Result<()> {
         match self.runtime_id {
             Some(id) if id == runtime_id => (),
-            Some(_) => return Err(KeyManagerError::RuntimeMismatch.into()),
+            Some(id) => {
+                return Err(KeyManagerError::RuntimeMismatch {
+                    expected: id,
+                    got: runtime_id,
+                }
+                .into())
+            }
             None => self.runtime_id = Some(runtime_id),
         }
         Ok(())
@@ -406,7 +416,8 @@ impl Kdf {
         // On startup load all master secrets.
         if next_generation == 0 {
             loop {
-                let secret = match Self::load_master_secret(storage, &runtime_id, next_generation) {
+                let secret = Self::load_master_secret(storage, &runtime_id, next_generation)?;
+                let secret = match secret {
                     Some(secret) => secret,
                     None => break,
                 };
@@ -446,7 +457,7 @@ impl Kdf {
         let mut last_checksum = checksum.clone();
         for generation in (next_generation..=generation).rev() {
             // Check the local storage first.
-            let secret = Self::load_master_secret(storage, &runtime_id, generation);
+            let secret = Self::load_master_secret(storage, &runtime_id, generation)?;
             if let Some(secret) = secret {
                 // Previous checksum is untrusted and needs to be verified.
                 let prev_checksum = Self::load_checksum(storage, generation);
@@ -489,13 +500,28 @@ impl Kdf {
         }
 
         // Replication finished, verify the final state.
-        if next_generation > generation + 1 || curr_checksum != last_checksum {
-            // The caller provided a checksum and a generation and replication produced a mismatch.
+        if next_generation > generation + 1 {
+            // The caller provided a generation which is older than the one stored locally.
             // The global key manager state disagrees with the enclave state.
             let mut inner = self.inner.write().unwrap();
             inner.reset();
             return Err(KeyManagerError::StateCorrupted.into());
         }
+        if curr_checksum != last_checksum {
+            // Locally stored secrets and secrets verified against the consensus checksum
+            // belong to different histories, so one of the sources has forked.
+            let generation = Self::find_divergence(
+                storage,
+                &runtime_id,
+                provider,
+                next_generation,
+                last_checksum,
+            );
+
+            let mut inner = self.inner.write().unwrap();
+            inner.reset();
+            return Err(KeyManagerError::DivergentState { generation }.into());
+        }
 
         // Update internal state.
         let mut inner = self.inner.write().unwrap();
@@ -503,7 +529,7 @@ impl Kdf {
 
         if inner.generation != Some(generation) {
             // Derive signing key from the latest secret.
-            let secret = Self::load_master_secret(storage, &runtime_id, generation)
+            let secret = Self::load_master_secret(storage, &runtime_id, generation)?
                 .ok_or(KeyManagerError::StateCorrupted)?;
 
             let sk = Self::derive_signing_key(&runtime_id, &secret);
@@ -526,6 +552,51 @@ impl Kdf {
         })
     }
 
+    /// Find the first generation at which the locally stored history differs from
+    /// the history verified against the consensus checksum.
+    ///
+    /// The verified history is walked backwards, starting with the checksum of all
+    /// generations preceding the given one, until it agrees with the local one.
+    /// If a master secret cannot be replicated, the earliest generation known to
+    /// differ is returned.
+    fn find_divergence(
+        storage: &dyn KeyValue,
+        runtime_id: &Namespace,
+        provider: &dyn SecretProvider,
+        mut generation: u64,
+        mut last_checksum: Vec<u8>,
+    ) -> u64 {
+        while generation > 0 {
+            generation -= 1;
+
+            let prev_checksum = provider.master_secret_iter(generation).find_map(|vs| {
+                let prev_checksum = if vs.checksum.is_empty() {
+                    runtime_id.0.to_vec()
+                } else {
+                    vs.checksum
+                };
+
+                let next_checksum = Self::checksum_master_secret(&vs.secret, &prev_checksum);
+                if next_checksum != last_checksum {
+                    return None;
+                }
+
+                Some(prev_checksum)
+            });
+
+            match prev_checksum {
+                Some(prev_checksum)
+                    if prev_checksum != Self::load_checksum(storage, generation) =>
+                {
+                    last_checksum = prev_checksum;
+                }
+                _ => break,
+            }
+        }
+
+        generation
+    }
+
     /// Key manager runtime ID.
     pub fn runtime_id(&self) -> Result<Namespace> {
         let inner = self.inner.read().unwrap();
@@ -562,7 +633,7 @@ impl Kdf {
         // Make sure the secret is loaded.
         if !inner.master_secrets.contains(&generation) {
             let runtime_id = inner.get_runtime_id()?;
-            let secret = match Self::load_master_secret(storage, &runtime_id, generation) {
+            let secret = match Self::load_master_secret(storage, &runtime_id, generation)? {
                 Some(secret) => secret,
                 None => {
                     inner.reset();
@@ -678,7 +749,7 @@ impl Kdf {
         // Then try to load it from the storage.
         // Don't update the cache as the caller could be replicating old secrets.
         let runtime_id = inner.get_runtime_id()?;
-        let secret = match Self::load_master_secret(storage, &runtime_id, generation) {
+        let secret = match Self::load_master_secret(storage, &runtime_id, generation)? {
             Some(secret) => secret,
             None => {
                 inner.reset();
@@ -758,31 +829,37 @@ impl Kdf {
     /// again. The Deoxys-II AEAD algorithm ensures that the secrets belong to the correct runtime
     /// and generation, while the consensus layer guarantees uniqueness, i.e. only one generation
     /// of the master secret can be published per key manager runtime.
+    ///
+    /// Returns `StorageCorrupted` if the persisted ciphertext is malformed, and `SealingError`
+    /// if it cannot be unsealed, e.g. because it was sealed by a different enclave or platform.
     fn load_master_secret(
         storage: &dyn KeyValue,
         runtime_id: &Namespace,
         generation: u64,
-    ) -> Option<Secret> {
+    ) -> Result<Option<Secret>> {
         // Fetch the encrypted master secret if it exists.
         let mut key = MASTER_SECRET_STORAGE_KEY_PREFIX.to_vec();
         key.extend(generation.to_le_bytes());
 
         let ciphertext = storage.get(key).unwrap();
         if ciphertext.is_empty() {
-            return None;
+            return Ok(None);
         }
 
-        let (ciphertext, nonce) = unpack_encrypted_secret_nonce(&ciphertext)
-            .expect("persisted state is corrupted, invalid size");
+        let (ciphertext, nonce) =
+            unpack_encrypted_secret_nonce(&ciphertext).ok_or(KeyManagerError::StorageCorrupted)?;
         let additional_data = pack_runtime_id_generation(runtime_id, generation);
 
         // Decrypt the persisted master secret.
         let d2 = new_deoxysii(Keypolicy::MRENCLAVE, MASTER_SECRET_SEAL_CONTEXT);
         let plaintext = d2
             .open(&nonce, ciphertext.to_vec(), additional_data)
-            .unwrap();
+            .map_err(|err| KeyManagerError::SealingError(err.to_string()))?;
+        let secret = plaintext
+            .try_into()
+            .map_err(|_| KeyManagerError::StorageCorrupted)?;
 
-        Some(Secret(plaintext.try_into().unwrap()))
+        Ok(Some(Secret(secret)))
     }
 
     /// Encrypt and store the master secret to untrusted local storage.
@@ -960,7 +1037,6 @@ mod tests {
         collections::{HashMap, HashSet},
         convert::TryInto,
         num::NonZeroUsize,
-        panic,
         sync::{Arc, RwLock},
         vec,
     };
@@ -986,9 +1062,9 @@ mod tests {
                 MASTER_SECRET_CHECKSUM_STORAGE_KEY_PREFIX, MASTER_SECRET_STORAGE_KEY_PREFIX,
                 RUNTIME_SIGNING_KEY_CUSTOM,
             },
-            KeyPairId, Secret, SECRET_SIZE,
+            KeyPairId, Secret, VerifiableSecret, SECRET_SIZE,
         },
-        secrets::MockSecretProvider,
+        secrets::{MockSecretProvider, SecretProvider},
     };
 
     use super::{
@@ -1004,6 +1080,48 @@ mod tests {
         }
     }
 
+    /// Secret provider whose master secrets diverge from the ones generated
+    /// by the mock secret provider starting with the given generation.
+    struct ForkedSecretProvider {
+        runtime_id: Namespace,
+        fork: u64,
+    }
+
+    impl ForkedSecretProvider {
+        fn master_secret(&self, generation: u64) -> Secret {
+            let offset = if generation >= self.fork { 100 } else { 0 };
+            Secret([generation as u8 + offset; SECRET_SIZE])
+        }
+
+        fn checksum_master_secret(&self, generation: u64) -> Vec<u8> {
+            (0..=generation).fold(self.runtime_id.0.to_vec(), |checksum, generation| {
+                Kdf::checksum_master_secret(&self.master_secret(generation), &checksum)
+            })
+        }
+    }
+
+    impl SecretProvider for ForkedSecretProvider {
+        fn master_secret_iter(
+            &self,
+            generation: u64,
+        ) -> Box<dyn Iterator<Item = VerifiableSecret> + '_> {
+            let secret = self.master_secret(generation);
+            let checksum = match generation {
+                0 => self.runtime_id.0.to_vec(),
+                generation => self.checksum_master_secret(generation - 1),
+            };
+
+            Box::new(std::iter::once(VerifiableSecret { secret, checksum }))
+        }
+
+        fn ephemeral_secret_iter(
+            &self,
+            _epoch: EpochTime,
+        ) -> Box<dyn Iterator<Item = Secret> + '_> {
+            Box::new(std::iter::empty())
+        }
+    }
+
     impl Default for Kdf {
         fn default() -> Self {
             let mut master_secrets = LruCache::new(NonZeroUsize::new(10).unwrap());
@@ -1251,17 +1369,69 @@ mod tests {
 
         // Init.
         let kdf = Kdf::new();
-        let result = panic::catch_unwind(|| {
-            kdf.init(
-                &storage,
-                runtime_id,
-                generation,
-                checksum.clone(),
-                epoch,
-                &provider,
-            )
-        });
-        assert!(result.is_err());
+        let result = kdf.init(
+            &storage,
+            runtime_id,
+            generation,
+            checksum.clone(),
+            epoch,
+            &provider,
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::StorageCorrupted.to_string()
+        );
+    }
+
+    #[test]
+    fn init_unsealable_secret() {
+        let kdf = Kdf::new();
+        let storage = UntrustedInMemoryStorage::new();
+        let runtime_id = Namespace::from(vec![1u8; 32]);
+        let epoch = 0;
+        let provider = MockSecretProvider::new(runtime_id, false);
+
+        // Init.
+        let generation = 5;
+        let checksum = provider.checksum_master_secret(generation);
+
+        let result = kdf.init(
+            &storage,
+            runtime_id,
+            generation,
+            checksum.clone(),
+            epoch,
+            &provider,
+        );
+        assert!(result.is_ok());
+
+        // Replace master secret with one sealed for another generation, which is structurally
+        // valid but cannot be unsealed.
+        let secret = provider.master_secret(generation);
+        Kdf::store_master_secret(&storage, &runtime_id, &secret, generation + 1);
+
+        let mut key = MASTER_SECRET_STORAGE_KEY_PREFIX.to_vec();
+        key.extend((generation + 1).to_le_bytes());
+        let ciphertext = storage.get(key).expect("secret should be fetched");
+
+        let mut key = MASTER_SECRET_STORAGE_KEY_PREFIX.to_vec();
+        key.extend(generation.to_le_bytes());
+        storage
+            .insert(key, ciphertext)
+            .expect("secret should be inserted");
+
+        // Init.
+        let kdf = Kdf::new();
+        let result = kdf.init(
+            &storage,
+            runtime_id,
+            generation,
+            checksum.clone(),
+            epoch,
+            &provider,
+        );
+        let err = result.unwrap_err().downcast::<KeyManagerError>();
+        assert!(matches!(err, Ok(KeyManagerError::SealingError(_))));
     }
 
     #[test]
@@ -1305,6 +1475,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn init_divergent_state() {
+        let kdf = Kdf::new();
+        let storage = UntrustedInMemoryStorage::new();
+        let runtime_id = Namespace::from(vec![1u8; 32]);
+        let epoch = 0;
+        let provider = MockSecretProvider::new(runtime_id, false);
+
+        // Init.
+        let generation = 5;
+        let checksum = provider.checksum_master_secret(generation);
+
+        let result = kdf.init(
+            &storage,
+            runtime_id,
+            generation,
+            checksum.clone(),
+            epoch,
+            &provider,
+        );
+        assert!(result.is_ok());
+
+        // Init from a source whose history forked at generation 3. Replicated secrets
+        // match the consensus checksum, but not the ones stored locally.
+        let provider = ForkedSecretProvider {
+            runtime_id,
+            fork: 3,
+        };
+        let generation = 7;
+        let checksum = provider.checksum_master_secret(generation);
+
+        let kdf = Kdf::new();
+        let result = kdf.init(
+            &storage,
+            runtime_id,
+            generation,
+            checksum.clone(),
+            epoch,
+            &provider,
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::DivergentState { generation: 3 }.to_string()
+        );
+    }
+
     #[test]
     fn init_invalid_runtime_id() {
         let kdf = Kdf::new();
@@ -1322,7 +1538,11 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err().to_string(),
-            KeyManagerError::RuntimeMismatch.to_string()
+            KeyManagerError::RuntimeMismatch {
+                expected: runtime_id,
+                got: invalid_runtime_id,
+            }
+            .to_string()
         );
 
         // Few secrets.
@@ -1350,7 +1570,11 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err().to_string(),
-            KeyManagerError::RuntimeMismatch.to_string()
+            KeyManagerError::RuntimeMismatch {
+                expected: runtime_id,
+                got: invalid_runtime_id,
+            }
+            .to_string()
         );
     }
 
@@ -1823,21 +2047,35 @@ mod tests {
         let generation = 3;
 
         // Empty storage.
-        let result = Kdf::load_master_secret(&storage, &runtime_id, generation);
+        let result = Kdf::load_master_secret(&storage, &runtime_id, generation)
+            .expect("loading should succeed");
         assert!(result.is_none());
 
         // Happy path.
         Kdf::store_master_secret(&storage, &runtime_id, &secret, generation);
         let loaded = Kdf::load_master_secret(&storage, &runtime_id, generation)
+            .expect("loading should succeed")
             .expect("master secret should be loaded");
         assert_eq!(secret.0, loaded.0);
 
-        // Decryption panics (invalid runtime ID).
+        // Decryption fails (invalid runtime ID).
         let invalid_runtime_id = Namespace([3; NAMESPACE_SIZE]);
-        let result = panic::catch_unwind(|| {
-            Kdf::load_master_secret(&storage, &invalid_runtime_id, generation)
-        });
-        assert!(result.is_err());
+        let result = Kdf::load_master_secret(&storage, &invalid_runtime_id, generation);
+        let err = result.unwrap_err().downcast::<KeyManagerError>();
+        assert!(matches!(err, Ok(KeyManagerError::SealingError(_))));
+
+        // Invalid size.
+        let mut key = MASTER_SECRET_STORAGE_KEY_PREFIX.to_vec();
+        key.extend(generation.to_le_bytes());
+        storage
+            .insert(key, vec![1, 2, 3])
+            .expect("secret should be inserted");
+
+        let result = Kdf::load_master_secret(&storage, &runtime_id, generation);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::StorageCorrupted.to_string()
+        );
     }
 
     #[test]
@@ -1,7 +1,7 @@
-use std::sync::Arc;
+use std::{convert::TryFrom, sync::Arc};
 
 use anyhow::Result;
-use rand::{rngs::OsRng, Rng};
+use rand::{rngs::OsRng, Rng, RngCore};
 use thiserror::Error;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
@@ -17,6 +17,8 @@ use oasis_core_runtime::{
     impl_bytes,
 };
 
+use crate::api::KeyManagerError;
+
 /// Context used for the public key signature.
 const PUBLIC_KEY_SIGNATURE_CONTEXT: &[u8] = b"oasis-core/keymanager: pk signature";
 
@@ -65,6 +67,21 @@ impl Secret {
 
         secret
     }
+
+    /// Generate a new random secret, failing with `InsufficientEntropy`
+    /// if the secure random number generator is not ready.
+    pub fn try_generate() -> Result<Self, KeyManagerError> {
+        Self::try_generate_with(&mut OsRng {})
+    }
+
+    /// Generate a new random secret using the given random number generator.
+    pub fn try_generate_with<R: RngCore>(rng: &mut R) -> Result<Self, KeyManagerError> {
+        let mut secret = Secret::default();
+        rng.try_fill_bytes(&mut secret.0)
+            .map_err(|_| KeyManagerError::InsufficientEntropy)?;
+
+        Ok(secret)
+    }
 }
 
 impl AsRef<[u8]> for Secret {
@@ -73,6 +90,27 @@ impl AsRef<[u8]> for Secret {
     }
 }
 
+impl TryFrom<Vec<u8>> for Secret {
+    type Error = KeyManagerError;
+
+    fn try_from(mut data: Vec<u8>) -> Result<Self, Self::Error> {
+        let got_len = data.len();
+        if got_len != SECRET_SIZE {
+            data.zeroize();
+            return Err(KeyManagerError::InvalidKeyMaterial {
+                expected_len: SECRET_SIZE,
+                got_len,
+            });
+        }
+
+        let mut secret = Secret::default();
+        secret.0.copy_from_slice(&data);
+        data.zeroize();
+
+        Ok(secret)
+    }
+}
+
 /// A secret with a checksum of the preceding secret.
 #[derive(Clone, Default, cbor::Encode, cbor::Decode)]
 pub struct VerifiableSecret {
@@ -260,7 +298,9 @@ impl SignedPublicKey {
 
 #[cfg(test)]
 mod test {
-    use std::sync::Arc;
+    use std::{convert::TryFrom, sync::Arc};
+
+    use rand::RngCore;
 
     use oasis_core_runtime::{
         common::{
@@ -273,9 +313,12 @@ mod test {
         consensus::beacon::EpochTime,
     };
 
-    use crate::crypto::{
-        types::MAX_SIGNED_EPHEMERAL_PUBLIC_KEY_AGE, KeyPairId, Secret, SignedPublicKey, StateKey,
-        SECRET_SIZE, STATE_KEY_SIZE,
+    use crate::{
+        api::KeyManagerError,
+        crypto::{
+            types::MAX_SIGNED_EPHEMERAL_PUBLIC_KEY_AGE, KeyPairId, Secret, SignedPublicKey,
+            StateKey, SECRET_SIZE, STATE_KEY_SIZE,
+        },
     };
 
     #[test]
@@ -450,6 +493,69 @@ mod test {
         assert_eq!(result.unwrap_err().to_string(), "invalid signature");
     }
 
+    #[test]
+    fn test_secret_try_from() {
+        // Happy path.
+        let secret = Secret::try_from(vec![1; SECRET_SIZE]).expect("secret should be parsed");
+        assert_eq!(secret.0, [1; SECRET_SIZE]);
+
+        // Truncated key material.
+        let result = Secret::try_from(vec![1; SECRET_SIZE - 1]);
+        assert!(matches!(
+            result,
+            Err(KeyManagerError::InvalidKeyMaterial {
+                expected_len: SECRET_SIZE,
+                got_len
+            }) if got_len == SECRET_SIZE - 1
+        ));
+
+        // Oversized key material.
+        let result = Secret::try_from(vec![1; SECRET_SIZE + 1]);
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            KeyManagerError::InvalidKeyMaterial {
+                expected_len: SECRET_SIZE,
+                got_len: SECRET_SIZE + 1,
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_secret_try_generate() {
+        /// Random number generator which is never ready.
+        struct NotReadyRng;
+
+        impl RngCore for NotReadyRng {
+            fn next_u32(&mut self) -> u32 {
+                unreachable!()
+            }
+
+            fn next_u64(&mut self) -> u64 {
+                unreachable!()
+            }
+
+            fn fill_bytes(&mut self, _dest: &mut [u8]) {
+                unreachable!()
+            }
+
+            fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), rand::Error> {
+                Err(rand::Error::new("not ready"))
+            }
+        }
+
+        // Happy path.
+        let secret = Secret::try_generate().expect("secret should be generated");
+        assert_ne!(secret.0, [0; SECRET_SIZE]);
+
+        // Random number generator not ready.
+        let err = Secret::try_generate_with(&mut NotReadyRng)
+            .err()
+            .expect("secret should not be generated");
+        assert!(matches!(err, KeyManagerError::InsufficientEntropy));
+        assert!(err.is_transient());
+    }
+
     #[test]
     fn test_zeroize_on_drop() {
         // Prepare secret and state key.
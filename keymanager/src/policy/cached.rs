@@ -1,7 +1,7 @@
 //! Policy support.
 use std::{
     cmp::Ordering,
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     sync::RwLock,
 };
 
@@ -18,7 +18,10 @@ use oasis_core_runtime::{
             EnclaveIdentity,
         },
     },
-    consensus::{beacon::EpochTime, keymanager::SignedPolicySGX},
+    consensus::{
+        beacon::EpochTime,
+        keymanager::{PolicySGX, SignedPolicySGX},
+    },
     storage::KeyValue,
 };
 
@@ -132,14 +135,8 @@ impl Policy {
 
     /// Check if the MRENCLAVE/MRSIGNER may replicate.
     pub fn may_replicate_secret(&self, remote_enclave: &EnclaveIdentity) -> Result<()> {
-        // Always allow replication to ourselves, if it is possible to do so in
-        // an authenticated manner.
-        #[cfg(any(target_env = "sgx", feature = "debug-mock-sgx"))]
-        {
-            let our_id = EnclaveIdentity::current().expect("failed to query MRENCLAVE/MRSIGNER");
-            if our_id == *remote_enclave {
-                return Ok(());
-            }
+        if Self::is_current_enclave(remote_enclave) {
+            return Ok(());
         }
 
         let inner = self.inner.read().unwrap();
@@ -154,6 +151,73 @@ impl Policy {
         }
     }
 
+    /// Check if the MRENCLAVE/MRSIGNER may replicate the given generation
+    /// of the master secret.
+    ///
+    /// Unlike `may_replicate_secret`, a refusal by the policy is reported
+    /// as `ReplicationDisallowedByPolicy`.
+    pub fn may_replicate_master_secret(
+        &self,
+        remote_enclave: &EnclaveIdentity,
+        generation: u64,
+    ) -> Result<()> {
+        if Self::is_current_enclave(remote_enclave) {
+            return Ok(());
+        }
+
+        let inner = self.inner.read().unwrap();
+        let policy = inner
+            .policy
+            .as_ref()
+            .ok_or(KeyManagerError::NotAuthorized)?;
+
+        policy.may_replicate_master_secret(remote_enclave, generation)?;
+        Ok(())
+    }
+
+    /// Check if the given generation of the master secret may be generated.
+    ///
+    /// Master secret rotations are disabled if the rotation interval is zero,
+    /// in which case only the first generation may be generated.
+    pub fn may_generate_master_secret(&self, generation: u64) -> Result<()> {
+        let inner = self.inner.read().unwrap();
+        let policy = inner
+            .policy
+            .as_ref()
+            .ok_or(KeyManagerError::NotAuthorized)?;
+
+        policy.may_generate_master_secret(generation)?;
+        Ok(())
+    }
+
+    /// Check if the MRENCLAVE/MRSIGNER belongs to the current enclave.
+    ///
+    /// Replication to ourselves is always allowed, if it is possible to do so in
+    /// an authenticated manner.
+    #[allow(unused_variables)]
+    fn is_current_enclave(remote_enclave: &EnclaveIdentity) -> bool {
+        #[cfg(any(target_env = "sgx", feature = "debug-mock-sgx"))]
+        {
+            let our_id = EnclaveIdentity::current().expect("failed to query MRENCLAVE/MRSIGNER");
+            if our_id == *remote_enclave {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Return the runtimes which any enclave in the policy authorizes to query keys,
+    /// sorted by runtime ID and without duplicates.
+    pub fn authorized_runtimes(&self) -> Vec<Namespace> {
+        let inner = self.inner.read().unwrap();
+        inner
+            .policy
+            .as_ref()
+            .map(|policy| policy.authorized_runtimes.clone())
+            .unwrap_or_default()
+    }
+
     /// Return the set of enclave identities we are allowed to replicate from.
     pub fn may_replicate_from(&self) -> Option<HashSet<EnclaveIdentity>> {
         let inner = self.inner.read().unwrap();
@@ -202,6 +266,7 @@ struct CachedPolicy {
     pub may_query: HashMap<Namespace, HashSet<EnclaveIdentity>>,
     pub may_replicate: HashSet<EnclaveIdentity>,
     pub may_replicate_from: HashSet<EnclaveIdentity>,
+    pub authorized_runtimes: Vec<Namespace>,
     pub master_secret_rotation_interval: EpochTime,
     pub max_ephemeral_secret_age: EpochTime,
 }
@@ -220,6 +285,9 @@ impl CachedPolicy {
         cached_policy.serial = policy.serial;
         cached_policy.runtime_id = policy.id;
         cached_policy.checksum = checksum;
+        cached_policy.authorized_runtimes = Self::collect_authorized_runtimes(policy);
+        cached_policy.master_secret_rotation_interval = policy.master_secret_rotation_interval;
+        cached_policy.max_ephemeral_secret_age = policy.max_ephemeral_secret_age;
 
         // Convert the policy into a cached one.
         let enclave_identity = match EnclaveIdentity::current() {
@@ -246,12 +314,13 @@ impl CachedPolicy {
             }
         }
 
-        cached_policy.master_secret_rotation_interval = policy.master_secret_rotation_interval;
-        cached_policy.max_ephemeral_secret_age = policy.max_ephemeral_secret_age;
-
         Ok(cached_policy)
     }
 
+    fn collect_authorized_runtimes(policy: &PolicySGX) -> Vec<Namespace> {
+        authorized_runtimes(policy).into_iter().collect()
+    }
+
     fn may_get_or_create_keys(
         &self,
         remote_enclave: &EnclaveIdentity,
@@ -268,6 +337,26 @@ impl CachedPolicy {
         self.may_replicate.contains(remote_enclave)
     }
 
+    fn may_replicate_master_secret(
+        &self,
+        remote_enclave: &EnclaveIdentity,
+        generation: u64,
+    ) -> Result<(), KeyManagerError> {
+        match self.may_replicate_secret(remote_enclave) {
+            true => Ok(()),
+            false => Err(KeyManagerError::ReplicationDisallowedByPolicy { generation }),
+        }
+    }
+
+    fn may_generate_master_secret(&self, generation: u64) -> Result<(), KeyManagerError> {
+        if generation > 0 && self.master_secret_rotation_interval == 0 {
+            return Err(KeyManagerError::FeatureDisabled {
+                feature: "master secret rotation".to_string(),
+            });
+        }
+        Ok(())
+    }
+
     fn checksum_policy(raw: &[u8]) -> Vec<u8> {
         let mut sha3 = Sha3::v256();
         sha3.update(raw);
@@ -276,3 +365,126 @@ impl CachedPolicy {
         k.to_vec()
     }
 }
+
+/// Runtimes which may query keys under the given policy, in any enclave.
+pub(super) fn authorized_runtimes(policy: &PolicySGX) -> BTreeSet<Namespace> {
+    policy
+        .enclaves
+        .values()
+        .flat_map(|enclave_policy| enclave_policy.may_query.keys().copied())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use oasis_core_runtime::{
+        common::{
+            namespace::Namespace,
+            sgx::{EnclaveIdentity, MrEnclave, MrSigner},
+        },
+        consensus::keymanager::{EnclavePolicySGX, PolicySGX},
+    };
+
+    use crate::api::KeyManagerError;
+
+    use super::CachedPolicy;
+
+    #[test]
+    fn test_may_replicate_master_secret() {
+        let allowed = EnclaveIdentity {
+            mr_enclave: MrEnclave::from(vec![1; 32]),
+            mr_signer: MrSigner::from(vec![2; 32]),
+        };
+        let denied = EnclaveIdentity {
+            mr_enclave: MrEnclave::from(vec![3; 32]),
+            mr_signer: MrSigner::from(vec![2; 32]),
+        };
+
+        let mut policy = CachedPolicy::default();
+        policy.may_replicate.insert(allowed.clone());
+
+        policy
+            .may_replicate_master_secret(&allowed, 1)
+            .expect("replication should be allowed");
+
+        let result = policy.may_replicate_master_secret(&denied, 1);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::ReplicationDisallowedByPolicy { generation: 1 }.to_string()
+        );
+    }
+
+    #[test]
+    fn test_may_generate_master_secret() {
+        let mut policy = CachedPolicy::default();
+
+        // Rotations disabled.
+        policy
+            .may_generate_master_secret(0)
+            .expect("first generation should be allowed");
+
+        let result = policy.may_generate_master_secret(1);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::FeatureDisabled {
+                feature: "master secret rotation".to_string(),
+            }
+            .to_string()
+        );
+
+        // Rotations enabled.
+        policy.master_secret_rotation_interval = 1;
+        for generation in 0..3 {
+            policy
+                .may_generate_master_secret(generation)
+                .expect("rotation should be allowed");
+        }
+    }
+
+    #[test]
+    fn test_authorized_runtimes() {
+        let enclave = |b| EnclaveIdentity {
+            mr_enclave: MrEnclave::from(vec![b; 32]),
+            mr_signer: MrSigner::from(vec![b; 32]),
+        };
+        let runtime_1 = Namespace::from(vec![1; 32]);
+        let runtime_2 = Namespace::from(vec![2; 32]);
+        let runtime_3 = Namespace::from(vec![3; 32]);
+
+        // Both enclaves authorize the second runtime.
+        let policy = PolicySGX {
+            enclaves: HashMap::from([
+                (
+                    enclave(1),
+                    EnclavePolicySGX {
+                        may_query: HashMap::from([
+                            (runtime_2, vec![enclave(10)]),
+                            (runtime_1, vec![enclave(11)]),
+                        ]),
+                        may_replicate: vec![],
+                    },
+                ),
+                (
+                    enclave(2),
+                    EnclavePolicySGX {
+                        may_query: HashMap::from([
+                            (runtime_3, vec![enclave(12)]),
+                            (runtime_2, vec![enclave(13)]),
+                        ]),
+                        may_replicate: vec![],
+                    },
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let runtimes = CachedPolicy::collect_authorized_runtimes(&policy);
+        assert_eq!(runtimes, vec![runtime_1, runtime_2, runtime_3]);
+
+        // Empty policy.
+        let runtimes = CachedPolicy::collect_authorized_runtimes(&PolicySGX::default());
+        assert!(runtimes.is_empty());
+    }
+}
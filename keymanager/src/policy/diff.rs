@@ -0,0 +1,195 @@
+//! Policy diffs.
+use std::{cmp::Ordering, collections::BTreeSet, fmt};
+
+use oasis_core_runtime::{
+    common::{crypto::signature::PublicKey, namespace::Namespace},
+    consensus::keymanager::SignedPolicySGX,
+};
+
+use crate::api::KeyManagerError;
+
+use super::cached::authorized_runtimes;
+
+/// Differences between two key manager policies.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PolicyDiff {
+    /// Serial number of the old policy.
+    pub old_serial: u32,
+    /// Serial number of the new policy.
+    pub new_serial: u32,
+    /// Runtimes which may query keys under the new policy, but not under the old one.
+    pub added_runtimes: Vec<Namespace>,
+    /// Runtimes which may query keys under the old policy, but not under the new one.
+    pub removed_runtimes: Vec<Namespace>,
+    /// Signers of the new policy which did not sign the old one.
+    pub added_signers: Vec<PublicKey>,
+    /// Signers of the old policy which did not sign the new one.
+    pub removed_signers: Vec<PublicKey>,
+    /// True iff the policies differ in anything other than the serial number.
+    pub changed: bool,
+}
+
+impl PolicyDiff {
+    /// Difference between the new and the old serial number.
+    pub fn serial_delta(&self) -> i64 {
+        self.new_serial as i64 - self.old_serial as i64
+    }
+
+    /// Verify that the new policy could replace the old one, i.e. that its serial
+    /// number was not decreased and that it was incremented if the policy changed.
+    pub fn verify_serial(&self) -> Result<(), KeyManagerError> {
+        match self.old_serial.cmp(&self.new_serial) {
+            Ordering::Greater => Err(KeyManagerError::PolicyRollback),
+            Ordering::Equal if self.changed => Err(KeyManagerError::PolicyChanged),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for PolicyDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "serial: {} -> {}", self.old_serial, self.new_serial)?;
+        for runtime_id in &self.added_runtimes {
+            writeln!(f, "+ runtime {}", runtime_id)?;
+        }
+        for runtime_id in &self.removed_runtimes {
+            writeln!(f, "- runtime {}", runtime_id)?;
+        }
+        for signer in &self.added_signers {
+            writeln!(f, "+ signer {}", signer)?;
+        }
+        for signer in &self.removed_signers {
+            writeln!(f, "- signer {}", signer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compute the differences between the old and the new policy.
+pub fn policy_diff(old: &SignedPolicySGX, new: &SignedPolicySGX) -> PolicyDiff {
+    let old_runtimes = authorized_runtimes(&old.policy);
+    let new_runtimes = authorized_runtimes(&new.policy);
+    let old_signers = signers(old);
+    let new_signers = signers(new);
+
+    // Ignore the serial number when checking whether the policy changed.
+    let mut new_with_old_serial = new.clone();
+    new_with_old_serial.policy.serial = old.policy.serial;
+
+    PolicyDiff {
+        old_serial: old.policy.serial,
+        new_serial: new.policy.serial,
+        added_runtimes: new_runtimes.difference(&old_runtimes).copied().collect(),
+        removed_runtimes: old_runtimes.difference(&new_runtimes).copied().collect(),
+        added_signers: new_signers.difference(&old_signers).copied().collect(),
+        removed_signers: old_signers.difference(&new_signers).copied().collect(),
+        changed: new_with_old_serial != *old,
+    }
+}
+
+fn signers(policy: &SignedPolicySGX) -> BTreeSet<PublicKey> {
+    policy.signatures.iter().map(|sig| sig.public_key).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use oasis_core_runtime::{
+        common::{
+            crypto::signature::{PublicKey, SignatureBundle},
+            namespace::Namespace,
+            sgx::{EnclaveIdentity, MrEnclave, MrSigner},
+        },
+        consensus::keymanager::{EnclavePolicySGX, PolicySGX, SignedPolicySGX},
+    };
+
+    use crate::api::KeyManagerError;
+
+    use super::policy_diff;
+
+    fn policy(serial: u32, runtimes: &[u8], signers: &[u8]) -> SignedPolicySGX {
+        let enclave = EnclaveIdentity {
+            mr_enclave: MrEnclave::from(vec![1; 32]),
+            mr_signer: MrSigner::from(vec![2; 32]),
+        };
+        let may_query = runtimes
+            .iter()
+            .map(|&b| (Namespace::from(vec![b; 32]), vec![enclave.clone()]))
+            .collect();
+
+        SignedPolicySGX {
+            policy: PolicySGX {
+                serial,
+                enclaves: HashMap::from([(
+                    enclave,
+                    EnclavePolicySGX {
+                        may_query,
+                        may_replicate: vec![],
+                    },
+                )]),
+                ..Default::default()
+            },
+            signatures: signers
+                .iter()
+                .map(|&b| SignatureBundle {
+                    public_key: PublicKey::from(vec![b; 32]),
+                    signature: Default::default(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_policy_diff_runtimes() {
+        let old = policy(1, &[1, 2], &[1, 2]);
+        let new = policy(2, &[2, 3], &[2, 3]);
+
+        let diff = policy_diff(&old, &new);
+        assert_eq!(diff.added_runtimes, vec![Namespace::from(vec![3; 32])]);
+        assert_eq!(diff.removed_runtimes, vec![Namespace::from(vec![1; 32])]);
+        assert_eq!(diff.added_signers, vec![PublicKey::from(vec![3; 32])]);
+        assert_eq!(diff.removed_signers, vec![PublicKey::from(vec![1; 32])]);
+        assert_eq!(diff.serial_delta(), 1);
+        assert!(diff.changed);
+        diff.verify_serial().expect("serial should be incremented");
+
+        let text = diff.to_string();
+        assert!(text.contains("serial: 1 -> 2"));
+        assert!(text.contains(&format!("+ runtime {}", Namespace::from(vec![3; 32]))));
+        assert!(text.contains(&format!("- runtime {}", Namespace::from(vec![1; 32]))));
+
+        // Identical policies.
+        let diff = policy_diff(&old, &old);
+        assert!(diff.added_runtimes.is_empty());
+        assert!(diff.removed_runtimes.is_empty());
+        assert!(!diff.changed);
+        diff.verify_serial()
+            .expect("identical policy should be accepted");
+    }
+
+    #[test]
+    fn test_policy_diff_serial() {
+        let old = policy(2, &[1], &[1]);
+
+        // Changed body without a serial increment.
+        let new = policy(2, &[1, 2], &[1]);
+        let diff = policy_diff(&old, &new);
+        assert!(diff.changed);
+        assert_eq!(diff.serial_delta(), 0);
+        assert_eq!(
+            diff.verify_serial().unwrap_err().to_string(),
+            KeyManagerError::PolicyChanged.to_string()
+        );
+
+        // Decreased serial.
+        let new = policy(1, &[1], &[1]);
+        let diff = policy_diff(&old, &new);
+        assert!(!diff.changed);
+        assert_eq!(diff.serial_delta(), -1);
+        assert_eq!(
+            diff.verify_serial().unwrap_err().to_string(),
+            KeyManagerError::PolicyRollback.to_string()
+        );
+    }
+}
@@ -1,7 +1,13 @@
 //! Policy support.
 mod cached;
+mod diff;
 mod global;
 mod signers;
 
 // Re-exports.
-pub use self::{cached::Policy, global::*, signers::TrustedSigners};
+pub use self::{
+    cached::Policy,
+    diff::{policy_diff, PolicyDiff},
+    global::*,
+    signers::TrustedSigners,
+};
@@ -16,6 +16,9 @@ pub struct TrustedSigners {
     pub signers: HashSet<OasisPublicKey>,
     /// Threshold for determining if enough valid signatures are present.
     pub threshold: u64,
+    /// Set of revoked signers whose signatures must not be present.
+    #[cbor(optional)]
+    pub revoked: HashSet<OasisPublicKey>,
 }
 
 #[cfg(feature = "debug-mock-sgx")]
@@ -46,6 +49,7 @@ impl TrustedSigners {
                 set
             },
             threshold: 2,
+            revoked: HashSet::new(),
         }
     }
 }
@@ -55,6 +59,7 @@ impl Default for TrustedSigners {
         Self {
             signers: HashSet::new(),
             threshold: 9001,
+            revoked: HashSet::new(),
         }
     }
 }
@@ -69,8 +74,20 @@ impl TrustedSigners {
         Ok(data)
     }
 
-    /// Verify that signed data has enough signatures from trusted signers.
+    /// Verify that signed data has enough signatures from trusted signers
+    /// and no signatures from revoked signers.
     fn verify_trusted_signers<P>(&self, signed_data: &impl SignedData<P>) -> Result<()> {
+        if let Some(sig) = signed_data
+            .signatures()
+            .iter()
+            .find(|s| self.revoked.contains(&s.public_key))
+        {
+            return Err(KeyManagerError::PolicySignerRevoked {
+                signer: sig.public_key,
+            }
+            .into());
+        }
+
         // Use set to remove duplicates.
         let all: HashSet<_> = signed_data
             .signatures()
@@ -121,6 +138,8 @@ mod tests {
     use crypto::signature::{PublicKey as OasisPublicKey, SignatureBundle};
     use oasis_core_runtime::{common::crypto, consensus::keymanager::SignedPolicySGX};
 
+    use crate::api::KeyManagerError;
+
     use super::TrustedSigners;
 
     #[test]
@@ -164,6 +183,7 @@ mod tests {
         let trusted_signers = TrustedSigners {
             signers: HashSet::from_iter(vec![public_keys[0], public_keys[1], public_keys[2]]),
             threshold: 2,
+            revoked: HashSet::new(),
         };
 
         // Happy path, enough trust (2/3).
@@ -197,4 +217,47 @@ mod tests {
             .verify_trusted_signers(&policy)
             .expect_err("policy should not be trusted");
     }
+
+    #[test]
+    fn test_revoked_policy_signers() {
+        let public_keys = vec![
+            OasisPublicKey::from(vec![1; 32]),
+            OasisPublicKey::from(vec![2; 32]),
+            OasisPublicKey::from(vec![3; 32]),
+        ];
+        let signatures: Vec<_> = public_keys
+            .iter()
+            .map(|&public_key| SignatureBundle {
+                public_key,
+                ..Default::default()
+            })
+            .collect();
+        let trusted_signers = TrustedSigners {
+            signers: HashSet::from_iter(public_keys.clone()),
+            threshold: 2,
+            revoked: HashSet::from_iter(vec![public_keys[2]]),
+        };
+
+        // Signatures from non-revoked signers only.
+        let policy = SignedPolicySGX {
+            signatures: signatures[..2].to_vec(),
+            ..Default::default()
+        };
+        trusted_signers
+            .verify_trusted_signers(&policy)
+            .expect("policy should be trusted");
+
+        // Signature from a revoked signer.
+        let policy = SignedPolicySGX {
+            signatures: signatures[1..].to_vec(),
+            ..Default::default()
+        };
+        let err = trusted_signers
+            .verify_trusted_signers(&policy)
+            .expect_err("policy should not be trusted");
+        assert!(matches!(
+            err.downcast::<KeyManagerError>(),
+            Ok(KeyManagerError::PolicySignerRevoked { signer }) if signer == public_keys[2]
+        ));
+    }
 }
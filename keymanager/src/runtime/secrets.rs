@@ -1,7 +1,7 @@
 //! Methods exported to remote clients via EnclaveRPC.
 use std::{
     collections::{HashMap, HashSet},
-    convert::TryInto,
+    convert::TryFrom,
     sync::Arc,
 };
 
@@ -19,6 +19,7 @@ use oasis_core_runtime::{
         },
         namespace::Namespace,
         sgx::EnclaveIdentity,
+        time::insecure_posix_time,
     },
     consensus::{
         beacon::EpochTime,
@@ -26,6 +27,7 @@ use oasis_core_runtime::{
             EncryptedEphemeralSecret, EncryptedMasterSecret, EncryptedSecret,
             SignedEncryptedEphemeralSecret, SignedEncryptedMasterSecret,
         },
+        registry::VerifiedAttestation,
         state::{
             beacon::ImmutableState as BeaconState,
             keymanager::{ImmutableState as KeyManagerState, Status},
@@ -62,7 +64,7 @@ use crate::{
     crypto::{
         kdf::{Kdf, State},
         pack_runtime_id_epoch, pack_runtime_id_generation_epoch, unpack_encrypted_secret_nonce,
-        KeyPair, Secret, SignedPublicKey, SECRET_SIZE,
+        KeyPair, Secret, SignedPublicKey,
     },
     policy::Policy,
     secrets::{KeyManagerSecretProvider, SecretProvider},
@@ -204,7 +206,7 @@ impl Secrets {
         ctx: &RpcContext,
         req: &ReplicateMasterSecretRequest,
     ) -> Result<ReplicateMasterSecretResponse> {
-        Self::authorize_secret_replication(ctx)?;
+        Self::authorize_master_secret_replication(ctx, req.generation)?;
         self.validate_height_freshness(req.height)?;
 
         let master_secret = Kdf::global().replicate_master_secret(&self.storage, req.generation)?;
@@ -243,10 +245,15 @@ impl Secrets {
             return Err(KeyManagerError::InvalidEpoch(epoch, req.epoch).into());
         }
 
+        // Rotations may be disabled by the policy.
+        let generation = req.generation;
+        if !Policy::unsafe_skip() {
+            Policy::global().may_generate_master_secret(generation)?;
+        }
+
         // Generate a secret and encrypt it.
         // Note that the checksum can be computed for the next generation only.
-        let generation = req.generation;
-        let secret = Secret::generate();
+        let secret = Secret::try_generate()?;
         let checksum = kdf.checksum_master_secret_proposal(runtime_id, &secret, generation)?;
         let additional_data = pack_runtime_id_generation_epoch(&runtime_id, generation, epoch);
         let secret = self.encrypt_secret(secret, checksum, additional_data, runtime_id)?;
@@ -279,7 +286,7 @@ impl Secrets {
         }
 
         // Generate a secret and encrypt it.
-        let secret = Secret::generate();
+        let secret = Secret::try_generate()?;
         let checksum = Kdf::checksum_ephemeral_secret(&runtime_id, &secret, epoch);
         let additional_data = pack_runtime_id_epoch(&runtime_id, epoch);
         let secret = self.encrypt_secret(secret, checksum, additional_data, runtime_id)?;
@@ -414,11 +421,7 @@ impl Secrets {
             &secret.secret.pub_key.0,
         )?;
 
-        if plaintext.len() != SECRET_SIZE {
-            return Err(KeyManagerError::InvalidCiphertext.into());
-        }
-
-        let secret = Secret(plaintext.try_into().expect("slice with incorrect length"));
+        let secret = Secret::try_from(plaintext)?;
 
         Ok(Some(secret))
     }
@@ -447,11 +450,7 @@ impl Secrets {
             &secret.secret.pub_key.0,
         )?;
 
-        if plaintext.len() != SECRET_SIZE {
-            return Err(KeyManagerError::InvalidCiphertext.into());
-        }
-
-        let secret = Secret(plaintext.try_into().expect("slice with incorrect length"));
+        let secret = Secret::try_from(plaintext)?;
 
         Ok(Some(secret))
     }
@@ -508,11 +507,38 @@ impl Secrets {
         Policy::global().may_replicate_secret(remote_enclave)
     }
 
+    /// Authorize the remote enclave so that the given generation of the master secret is never
+    /// replicated to an enclave which is not allowed to hold it.
+    fn authorize_master_secret_replication(ctx: &RpcContext, generation: u64) -> Result<()> {
+        if Policy::unsafe_skip() {
+            return Ok(()); // Authorize unsafe builds always.
+        }
+        let remote_enclave = Self::authenticate(ctx)?;
+        Policy::global().may_replicate_master_secret(remote_enclave, generation)
+    }
+
     /// Authenticate the remote enclave based on the MRSIGNER/MRENCLAVE/request.
     fn authenticate(ctx: &RpcContext) -> Result<&EnclaveIdentity> {
-        let si = ctx.session_info.as_ref();
-        let si = si.ok_or(KeyManagerError::NotAuthenticated)?;
-        Ok(&si.verified_attestation.quote.identity)
+        let attestation = ctx.session_info.as_ref().map(|si| &si.verified_attestation);
+        let identity = Self::verify_session_attestation(attestation, insecure_posix_time())?;
+        Ok(identity)
+    }
+
+    /// Verify that the session has been authenticated and that its attestation
+    /// is not older than the maximum quote age.
+    ///
+    /// Expired sessions are normally torn down by the RPC session demultiplexer before
+    /// the request is dispatched, so this only guards against attestations which expire
+    /// while the request is being processed.
+    fn verify_session_attestation(
+        attestation: Option<&VerifiedAttestation>,
+        now: i64,
+    ) -> Result<&EnclaveIdentity, KeyManagerError> {
+        let attestation = attestation.ok_or(KeyManagerError::NotAuthenticated)?;
+        if attestation.is_expired(now) {
+            return Err(KeyManagerError::SessionExpired);
+        }
+        Ok(&attestation.quote.identity)
     }
 
     /// Fetch current epoch from the consensus layer.
@@ -536,8 +562,26 @@ impl Secrets {
     /// too far in the future or too far back in the past.
     fn validate_ephemeral_key_epoch(&self, epoch: EpochTime) -> Result<()> {
         let consensus_epoch = self.consensus_epoch()?;
-        if consensus_epoch + 1 < epoch || consensus_epoch > epoch + MAX_EPHEMERAL_KEY_AGE {
-            return Err(KeyManagerError::InvalidEpoch(consensus_epoch, epoch).into());
+        Self::verify_ephemeral_key_epoch(consensus_epoch, epoch)?;
+        Ok(())
+    }
+
+    /// Verify that the ephemeral key epoch is within the allowed range around
+    /// the consensus epoch.
+    ///
+    /// Keys for epochs which are too far in the future cannot be accessed until
+    /// the consensus layer reaches the epoch preceding them.
+    fn verify_ephemeral_key_epoch(
+        consensus_epoch: EpochTime,
+        epoch: EpochTime,
+    ) -> Result<(), KeyManagerError> {
+        if consensus_epoch + 1 < epoch {
+            return Err(KeyManagerError::SecretNotYetAccessible {
+                available_at_epoch: epoch - 1,
+            });
+        }
+        if consensus_epoch > epoch + MAX_EPHEMERAL_KEY_AGE {
+            return Err(KeyManagerError::InvalidEpoch(consensus_epoch, epoch));
         }
         Ok(())
     }
@@ -743,3 +787,76 @@ impl Handler for Secrets {
         ]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use oasis_core_runtime::{
+        common::sgx::{VerifiedQuote, MAX_QUOTE_AGE},
+        consensus::registry::VerifiedAttestation,
+    };
+
+    use crate::api::KeyManagerError;
+
+    use super::{Secrets, MAX_EPHEMERAL_KEY_AGE};
+
+    #[test]
+    fn test_verify_session_attestation() {
+        let now = 1_000_000;
+        let attestation = VerifiedAttestation {
+            quote: VerifiedQuote {
+                timestamp: now - MAX_QUOTE_AGE,
+                ..Default::default()
+            },
+            height: None,
+        };
+
+        // Never authenticated.
+        let result = Secrets::verify_session_attestation(None, now);
+        let err = result.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            KeyManagerError::NotAuthenticated.to_string()
+        );
+        assert!(!err.is_transient());
+
+        // Fresh session.
+        let identity = Secrets::verify_session_attestation(Some(&attestation), now)
+            .expect("session should be authenticated");
+        assert_eq!(identity, &attestation.quote.identity);
+
+        // Expired session.
+        let result = Secrets::verify_session_attestation(Some(&attestation), now + 1);
+        let err = result.unwrap_err();
+        assert_eq!(err.to_string(), KeyManagerError::SessionExpired.to_string());
+        assert!(err.is_transient());
+    }
+
+    #[test]
+    fn test_verify_ephemeral_key_epoch() {
+        let epoch = 10;
+
+        // Before the unlock epoch.
+        let result = Secrets::verify_ephemeral_key_epoch(epoch - 2, epoch);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::SecretNotYetAccessible {
+                available_at_epoch: epoch - 1
+            }
+            .to_string()
+        );
+
+        // At and after the unlock epoch.
+        for consensus_epoch in epoch - 1..=epoch + MAX_EPHEMERAL_KEY_AGE {
+            Secrets::verify_ephemeral_key_epoch(consensus_epoch, epoch)
+                .expect("ephemeral key epoch should be valid");
+        }
+
+        // Too far in the past.
+        let consensus_epoch = epoch + MAX_EPHEMERAL_KEY_AGE + 1;
+        let result = Secrets::verify_ephemeral_key_epoch(consensus_epoch, epoch);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            KeyManagerError::InvalidEpoch(consensus_epoch, epoch).to_string()
+        );
+    }
+}
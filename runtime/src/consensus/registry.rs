@@ -752,6 +752,13 @@ pub struct VerifiedAttestation {
     pub height: Option<u64>,
 }
 
+impl VerifiedAttestation {
+    /// Whether the attestation has been verified more than the maximum quote age ago.
+    pub fn is_expired(&self, now: i64) -> bool {
+        now - self.quote.timestamp > sgx::MAX_QUOTE_AGE
+    }
+}
+
 impl From<sgx::VerifiedQuote> for VerifiedAttestation {
     fn from(quote: sgx::VerifiedQuote) -> Self {
         Self {
@@ -1481,4 +1488,19 @@ mod tests {
         assert!(!node.has_roles(RolesMask::ROLE_KEY_MANAGER));
         assert!(!node.has_roles(RolesMask::ROLE_STORAGE_RPC));
     }
+
+    #[test]
+    fn test_verified_attestation_is_expired() {
+        let now = 1_000_000;
+        let attestation = VerifiedAttestation {
+            quote: sgx::VerifiedQuote {
+                timestamp: now - sgx::MAX_QUOTE_AGE,
+                ..Default::default()
+            },
+            height: None,
+        };
+
+        assert!(!attestation.is_expired(now));
+        assert!(attestation.is_expired(now + 1));
+    }
 }
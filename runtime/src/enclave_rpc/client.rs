@@ -204,6 +204,34 @@ impl RpcClient {
         self.close_all(sessions).await;
     }
 
+    /// Close all sessions. New sessions are established on the next call.
+    pub async fn close_all_sessions(&self) {
+        let sessions = {
+            let mut sessions = self.sessions.lock().await;
+            sessions.drain()
+        };
+        self.close_all(sessions).await;
+    }
+
+    /// Close all sessions which have not been used for at least `idle_timeout` seconds.
+    ///
+    /// Sessions which are in use by in-flight calls are kept. Closed sessions are
+    /// re-established on the next call.
+    pub async fn close_idle_sessions(&self, idle_timeout: i64) {
+        let sessions = {
+            let mut sessions = self.sessions.lock().await;
+            sessions.remove_idle(idle_timeout, insecure_posix_time())
+        };
+
+        let futures = FuturesUnordered::new();
+        for session in sessions {
+            futures.push(async {
+                let _ = self.close(session).await; // Ignore errors.
+            });
+        }
+        futures.collect::<()>().await;
+    }
+
     /// Call a remote method using an encrypted and authenticated Noise session.
     pub async fn secure_call<C, O>(
         &self,
@@ -878,4 +906,112 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn test_rpc_client_close_all_sessions() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let _guard = rt.enter(); // Ensure Tokio runtime is available.
+        let transport = MockTransport::new();
+        let builder = session::Builder::default();
+        let client = RpcClient::new(Box::new(transport.clone()), builder, 8, 2, 60);
+
+        let result: u64 = rt
+            .block_on(async {
+                client
+                    .secure_call("test", 42, vec![])
+                    .await
+                    .into_result_with_feedback()
+                    .await
+            })
+            .unwrap();
+        assert_eq!(result, 42, "secure call should work");
+        assert_eq!(transport.take_peer_feedback_history().len(), 3);
+
+        // Close all sessions and make sure that the next call establishes a new one.
+        rt.block_on(client.close_all_sessions());
+
+        let result: u64 = rt
+            .block_on(async {
+                client
+                    .secure_call("test", 43, vec![])
+                    .await
+                    .into_result_with_feedback()
+                    .await
+            })
+            .unwrap();
+        assert_eq!(result, 43, "secure call should work");
+        assert_eq!(
+            transport.take_peer_feedback_history(),
+            vec![
+                // (4, ...), // Session close. [skipped]
+                (5, types::PeerFeedback::Success), // New handshake.
+                (6, types::PeerFeedback::Success), // New handshake.
+                (7, types::PeerFeedback::Success), // Handled call.
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rpc_client_idle_sessions() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let _guard = rt.enter(); // Ensure Tokio runtime is available.
+        let transport = MockTransport::new();
+        let builder = session::Builder::default();
+        let client = RpcClient::new(Box::new(transport.clone()), builder, 8, 2, 60);
+
+        let result: u64 = rt
+            .block_on(async {
+                client
+                    .secure_call("test", 42, vec![])
+                    .await
+                    .into_result_with_feedback()
+                    .await
+            })
+            .unwrap();
+        assert_eq!(result, 42, "secure call should work");
+        assert_eq!(transport.take_peer_feedback_history().len(), 3);
+
+        // Sessions which were used recently are kept.
+        rt.block_on(client.close_idle_sessions(60));
+
+        let result: u64 = rt
+            .block_on(async {
+                client
+                    .secure_call("test", 43, vec![])
+                    .await
+                    .into_result_with_feedback()
+                    .await
+            })
+            .unwrap();
+        assert_eq!(result, 43, "secure call should work");
+        assert_eq!(
+            transport.take_peer_feedback_history(),
+            vec![
+                (4, types::PeerFeedback::Success), // Handled call.
+            ]
+        );
+
+        // Idle sessions are closed and re-established on the next call.
+        rt.block_on(client.close_idle_sessions(0));
+
+        let result: u64 = rt
+            .block_on(async {
+                client
+                    .secure_call("test", 44, vec![])
+                    .await
+                    .into_result_with_feedback()
+                    .await
+            })
+            .unwrap();
+        assert_eq!(result, 44, "secure call should work");
+        assert_eq!(
+            transport.take_peer_feedback_history(),
+            vec![
+                // (5, ...), // Session close. [skipped]
+                (6, types::PeerFeedback::Success), // New handshake.
+                (7, types::PeerFeedback::Success), // New handshake.
+                (8, types::PeerFeedback::Success), // Handled call.
+            ]
+        );
+    }
 }
@@ -18,6 +18,8 @@ pub enum Error {
     MalformedPayload(#[from] cbor::DecodeError),
     #[error("malformed request method")]
     MalformedRequestMethod,
+    #[error("session expired")]
+    SessionExpired,
     #[error("sessions error: {0}")]
     SessionsError(#[from] sessions::Error),
     #[error("{0}")]
@@ -31,6 +33,7 @@ impl Error {
             Error::MalformedRequestMethod => 2,
             Error::SessionsError(_) => 3,
             Error::Other(_) => 4,
+            Error::SessionExpired => 5,
         }
     }
 }
@@ -116,6 +119,14 @@ impl Demux {
         let frame: Frame = cbor::from_slice(&data)?;
         // Get the existing session or create a new one.
         let mut session = self.get_or_create_session(peer_id, frame.session).await?;
+        // Remove sessions whose attestation has expired so that the peer re-handshakes.
+        if let Some(info) = session.info() {
+            if info.verified_attestation.is_expired(insecure_posix_time()) {
+                let mut sessions = self.sessions.lock().unwrap();
+                sessions.remove(&session);
+                return Err(Error::SessionExpired);
+            }
+        }
         // Process session data.
         match session.process_data(&frame.payload, writer).await {
             Ok(msg) => {
@@ -160,3 +171,98 @@ impl Demux {
         let _ = sessions.drain();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        common::{
+            sgx::{VerifiedQuote, MAX_QUOTE_AGE},
+            time::insecure_posix_time,
+        },
+        consensus::registry::VerifiedAttestation,
+        enclave_rpc::{
+            session::{Builder, RAKBinding, SessionInfo},
+            types::{Frame, SessionID},
+        },
+    };
+
+    use super::{Demux, Error};
+
+    fn session_info(timestamp: i64) -> Arc<SessionInfo> {
+        Arc::new(SessionInfo {
+            rak_binding: RAKBinding::V2 {
+                ect: Default::default(),
+                binding: Default::default(),
+            },
+            verified_attestation: VerifiedAttestation {
+                quote: VerifiedQuote {
+                    timestamp,
+                    ..Default::default()
+                },
+                height: None,
+            },
+            endorsed_by: None,
+        })
+    }
+
+    #[test]
+    fn test_process_frame_expired_session() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let demux = Demux::new(Builder::default(), 4, 4, 60);
+        let peer_id = vec![1];
+        let session_id = SessionID::random();
+
+        // Start a handshake, so that frames can be processed by the session.
+        let mut initiator = Builder::default().build_initiator();
+        let mut payload = vec![];
+        rt.block_on(initiator.process_data(&[], &mut payload))
+            .unwrap();
+        let frame = cbor::to_vec(Frame {
+            session: session_id,
+            untrusted_plaintext: "".to_string(),
+            payload,
+        });
+
+        // Session authenticated with a fresh attestation.
+        let now = insecure_posix_time();
+        rt.block_on(async {
+            let mut session = demux
+                .get_or_create_session(peer_id.clone(), session_id)
+                .await
+                .unwrap();
+            session.set_info(Some(session_info(now)));
+        });
+
+        let result = rt.block_on(demux.process_frame(peer_id.clone(), frame.clone(), vec![]));
+        assert!(result.is_ok());
+        drop(result);
+        assert!(demux
+            .sessions
+            .lock()
+            .unwrap()
+            .get(&peer_id, &session_id)
+            .is_some());
+
+        // Session authenticated with an attestation older than the maximum quote age.
+        rt.block_on(async {
+            let mut session = demux
+                .get_or_create_session(peer_id.clone(), session_id)
+                .await
+                .unwrap();
+            session.set_info(Some(session_info(now - MAX_QUOTE_AGE - 60)));
+        });
+
+        let result = rt.block_on(demux.process_frame(peer_id.clone(), frame, vec![]));
+        assert!(matches!(result, Err(Error::SessionExpired)));
+
+        // The session is torn down, so that the peer has to re-handshake.
+        assert!(demux
+            .sessions
+            .lock()
+            .unwrap()
+            .get(&peer_id, &session_id)
+            .is_none());
+    }
+}
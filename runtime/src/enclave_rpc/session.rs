@@ -272,6 +272,12 @@ impl Session {
         self.info.clone()
     }
 
+    /// Set session information.
+    #[cfg(test)]
+    pub(crate) fn set_session_info(&mut self, info: Option<Arc<SessionInfo>>) {
+        self.info = info;
+    }
+
     /// Whether the session handshake has completed and the session
     /// is in transport mode.
     pub fn is_connected(&self) -> bool {
@@ -66,6 +66,12 @@ impl<PeerID> MultiplexedSession<PeerID> {
         self.inner.session_info()
     }
 
+    /// Set session information.
+    #[cfg(test)]
+    pub(crate) fn set_info(&mut self, info: Option<Arc<SessionInfo>>) {
+        self.inner.set_session_info(info);
+    }
+
     /// Whether the session is in closed state.
     pub fn is_closed(&self) -> bool {
         self.inner.is_closed()
@@ -464,6 +470,39 @@ where
         Ok(Some(session))
     }
 
+    /// Remove all sessions which have not been accessed for at least `idle_timeout` seconds.
+    ///
+    /// Sessions which are currently in use are kept.
+    pub fn remove_idle(
+        &mut self,
+        idle_timeout: i64,
+        now: i64,
+    ) -> Vec<OwnedMutexGuard<MultiplexedSession<PeerID>>> {
+        let mut idle_sessions = vec![];
+
+        for (last_access_time, peer_id, session_id) in self.by_idle_time.iter() {
+            if now.saturating_sub(*last_access_time) < idle_timeout {
+                // All next sessions will be more fresh.
+                break;
+            }
+
+            // Fetch session and attempt to lock it.
+            if let Some(sessions) = self.by_peer.get(peer_id) {
+                if let Some(session) = sessions.get(session_id) {
+                    if let Ok(session) = session.inner.clone().try_lock_owned() {
+                        idle_sessions.push(session);
+                    }
+                }
+            }
+        }
+
+        for session in &idle_sessions {
+            self.remove(session);
+        }
+
+        idle_sessions
+    }
+
     /// Add a session if there is an available spot.
     pub fn add(
         &mut self,
@@ -947,4 +986,35 @@ mod test {
         assert_eq!(sessions.session_count(), 0);
         assert_eq!(sessions.peer_count(), 0);
     }
+
+    #[test]
+    fn test_remove_idle() {
+        let (peer_ids, session_ids) = ids();
+        let mut sessions = Sessions::new(Builder::default(), 8, 2, 60);
+
+        let test_vector = vec![
+            (&peer_ids[0], &session_ids[0], 10),
+            (&peer_ids[1], &session_ids[1], 20),
+            (&peer_ids[2], &session_ids[2], 30),
+        ];
+
+        let mut shared_sessions = vec![];
+        for (peer_id, session_id, now) in test_vector {
+            let session = sessions.create_responder(peer_id.clone(), session_id.clone());
+            shared_sessions.push(sessions.add(session, now).unwrap());
+        }
+
+        // No session has been idle long enough.
+        let removed_sessions = sessions.remove_idle(15, 24);
+        assert!(removed_sessions.is_empty());
+        assert_eq!(sessions.session_count(), 3);
+
+        // Idle sessions are removed, unless they are in use.
+        let _in_use = shared_sessions[1].clone().try_lock_owned().unwrap();
+        let removed_sessions = sessions.remove_idle(15, 40);
+        assert_eq!(removed_sessions.len(), 1);
+        assert_eq!(&removed_sessions[0].session_id, &session_ids[0]);
+        assert_eq!(sessions.session_count(), 2);
+        assert_eq!(sessions.peer_count(), 2);
+    }
 }
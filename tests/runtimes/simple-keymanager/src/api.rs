@@ -20,5 +20,6 @@ pub fn trusted_signers() -> TrustedSigners {
             set
         },
         threshold: 2,
+        revoked: HashSet::new(),
     }
 }